@@ -0,0 +1,66 @@
+use bevy::{prelude::*, render::camera::PerspectiveCameraBundle, DefaultPlugins};
+use bevy_prototype_particles::*;
+
+#[derive(Component)]
+struct Orbit;
+
+fn create_scene(mut commands: Commands) {
+    // camera
+    commands.spawn_bundle(PerspectiveCameraBundle {
+        transform: Transform::from_xyz(-2.0, 2.5, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..Default::default()
+    });
+}
+
+fn create_beams(mut commands: Commands, mut materials: ResMut<Assets<ParticleMaterial>>) {
+    let start = commands
+        .spawn()
+        .insert(Transform::from_xyz(0.0, 1.5, 0.0))
+        .insert(GlobalTransform::default())
+        .id();
+    let end = commands
+        .spawn()
+        .insert(Transform::from_xyz(1.5, -1.0, 0.0))
+        .insert(GlobalTransform::default())
+        .insert(Orbit)
+        .id();
+
+    commands
+        .spawn()
+        .insert(
+            ParticleBeam::new(start, end)
+                .with_segments(32)
+                .with_noise(0.3, 4.0, 2.0)
+                .with_branching(BeamBranching {
+                    depth: 2,
+                    ..Default::default()
+                })
+                .with_seed(7)
+                .with_width(curve::from_vec(vec![0.08, 0.02]))
+                .with_color(curve::from_vec(vec![
+                    Vec4::from((0.6, 0.8, 1.0, 1.0)),
+                    Vec4::from((0.3, 0.4, 1.0, 0.6)),
+                ])),
+        )
+        .insert(materials.add(ParticleMaterial::default()))
+        .insert(Visibility::default())
+        .insert(ComputedVisibility::default());
+}
+
+fn orbit(time: Res<Time>, mut query: Query<&mut Transform, With<Orbit>>) {
+    let angle = time.seconds_since_startup() as f32;
+    for mut transform in query.iter_mut() {
+        transform.translation = Vec3::new(1.5 * angle.cos(), -1.0, 1.5 * angle.sin());
+    }
+}
+
+fn main() {
+    App::new()
+        .insert_resource(Msaa { samples: 1 })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(ParticlePlugin)
+        .add_startup_system(create_scene.system())
+        .add_startup_system(create_beams.system())
+        .add_system(orbit.system())
+        .run()
+}
@@ -29,6 +29,7 @@ fn create_particles(
         })
         .insert(materials.add(ParticleMaterial {
             base_color_texture: Some(asset_server.load("icon.png")),
+            ..Default::default()
         }))
         .insert(modifiers::ColorByLifetime {
             color: curve::from_vec(vec![
@@ -38,9 +39,11 @@ fn create_particles(
                 Vec4::from((0.2, 0.0, 0.0, 0.15)),
                 Vec4::from((0.0, 0.0, 0.0, 0.0)),
             ]),
+            combine: modifiers::CombineMode::Replace,
         })
         .insert(modifiers::SizeOverLifetime {
             size: curve::from_constant_vec(vec![0.3, 0.2, 0.0]),
+            combine: modifiers::CombineMode::Replace,
         })
         .insert(modifiers::ConstantForce {
             acceleration_per_second: Vec3::from((0.0, 5.0, 0.0)),
@@ -50,6 +53,7 @@ fn create_particles(
                 .add_burst(EmitterBurst {
                     count: 50..100,
                     wait: Duration::from_millis(100),
+                    ..Default::default()
                 })
                 .with_default_color(Color::rgba(0.5, 0.0, 0.0, 0.1))
                 .with_default_lifetime(2.0)
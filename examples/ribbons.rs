@@ -0,0 +1,60 @@
+use bevy::{prelude::*, render::camera::PerspectiveCameraBundle, DefaultPlugins};
+use bevy_prototype_particles::*;
+
+use std::time::Duration;
+
+fn create_scene(mut commands: Commands) {
+    // camera
+    commands.spawn_bundle(PerspectiveCameraBundle {
+        transform: Transform::from_xyz(-2.0, 2.5, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..Default::default()
+    });
+}
+
+fn create_particles(mut commands: Commands, mut materials: ResMut<Assets<ParticleMaterial>>) {
+    commands
+        .spawn_bundle(ParticleBundle {
+            particles: Particles::new(100).with_history(32, 0.05),
+            transform: Transform {
+                translation: Vec3::from((0.0, -1.0, 0.0)),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(materials.add(ParticleMaterial::default()))
+        .insert(
+            ParticleRibbon::history()
+                .with_width(curve::from_vec(vec![1.0, 0.0]))
+                .with_color(curve::from_vec(vec![
+                    Vec4::from((1.0, 1.0, 1.0, 1.0)),
+                    Vec4::from((1.0, 1.0, 1.0, 0.0)),
+                ])),
+        )
+        .insert(modifiers::ConstantForce {
+            acceleration_per_second: Vec3::from((0.0, -2.0, 0.0)),
+        })
+        .insert(
+            ParticleEmitter::hemisphere(Vec3::ZERO, 0.5)
+                .add_burst(EmitterBurst {
+                    count: 2..4,
+                    wait: Duration::from_millis(200),
+                    ..Default::default()
+                })
+                .with_looping(true)
+                .with_default_color(Color::rgba(1.0, 0.5, 0.0, 1.0))
+                .with_default_lifetime(3.0)
+                .with_default_speed(3.0)
+                .with_default_size(0.1)
+                .build(),
+        );
+}
+
+fn main() {
+    App::new()
+        .insert_resource(Msaa { samples: 1 })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(ParticlePlugin)
+        .add_startup_system(create_scene.system())
+        .add_startup_system(create_particles.system())
+        .run()
+}
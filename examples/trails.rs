@@ -21,6 +21,7 @@ fn create_particles(
         .insert(particles.clone())
         .insert(materials.add(ParticleMaterial {
             base_color_texture: Some(asset_server.load("icon.png")),
+            ..Default::default()
         }))
         .insert(modifiers::ColorByLifetime {
             color: curve::from_vec(vec![
@@ -30,9 +31,11 @@ fn create_particles(
                 Vec4::from((0.2, 0.0, 0.0, 0.15)),
                 Vec4::from((0.0, 0.0, 0.0, 0.0)),
             ]),
+            combine: modifiers::CombineMode::Replace,
         })
         .insert(modifiers::SizeOverLifetime {
             size: curve::from_constant_vec(vec![0.3, 0.2, 0.0]),
+            combine: modifiers::CombineMode::Replace,
         })
         .insert(modifiers::ConstantForce {
             acceleration_per_second: Vec3::from((0.0, 5.0, 0.0)),
@@ -47,6 +50,7 @@ fn create_particles(
                 .add_burst(EmitterBurst {
                     count: 5..10,
                     wait: Duration::from_millis(300),
+                    ..Default::default()
                 })
                 .with_default_color(Color::rgba(0.5, 0.0, 0.0, 0.1))
                 .with_default_lifetime(2.0)
@@ -61,19 +65,18 @@ fn create_particles(
         .insert(particles)
         .insert(materials.add(ParticleMaterial {
             base_color_texture: Some(asset_server.load("icon.png")),
+            ..Default::default()
         }))
         .insert(modifiers::SizeOverLifetime {
             size: curve::from_constant_vec(vec![0.3, 0.2, 0.0]),
+            combine: modifiers::CombineMode::Replace,
         })
         .insert(Transform {
             translation: Vec3::from((0.0, -1.0, 0.0)),
             ..Default::default()
         })
         .insert(GlobalTransform::default())
-        .insert(TrailEmitter {
-            tracking: source,
-            lifetime: 1.0,
-        });
+        .insert(TrailEmitter::new(source, 1.0, TrailRate::PerDistance(20.0)));
 }
 
 fn main() {
@@ -0,0 +1,392 @@
+use crate::{
+    curve,
+    ribbon::{self, RibbonGeometry, RibbonPoint, RibbonUvMode},
+    shape::sample_cone,
+};
+use bevy::{
+    math::{curves::CurveFixed, *},
+    prelude::*,
+};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use std::{f32::consts::PI, ops::Range};
+
+/// How the points of a beam are displaced from the straight line between its ends.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BeamDisplacement {
+    None,
+    /// Offsets every point randomly, re-rolled every refresh.
+    Jitter {
+        amplitude: f32,
+    },
+    /// Offsets points by smooth noise along the beam that scrolls over time.
+    Noise {
+        amplitude: f32,
+        /// Noise cycles per beam length.
+        frequency: f32,
+        /// Noise cycles per second.
+        speed: f32,
+    },
+}
+
+/// Settings for forking a beam into smaller branches, as seen in lightning.
+#[derive(Debug, Clone)]
+pub struct BeamBranching {
+    /// The chance of each point of a strip starting a new branch.
+    pub chance: f32,
+    /// The length of each branch, relative to the strip it forks from.
+    pub length: Range<f32>,
+    /// The maximum angle between a branch and the strip it forks from, in radians.
+    pub angle: f32,
+    pub segments: usize,
+    /// Scales the width and displacement of each branch relative to its parent.
+    pub scale: f32,
+    /// How many times branches can fork again.
+    pub depth: u32,
+}
+
+impl Default for BeamBranching {
+    fn default() -> Self {
+        Self {
+            chance: 0.1,
+            length: 0.2..0.4,
+            angle: PI / 4.0,
+            segments: 6,
+            scale: 0.5,
+            depth: 1,
+        }
+    }
+}
+
+/// A segmented beam rendered as a ribbon between two entities.
+///
+/// The beam is regenerated every frame from the [`GlobalTransform`] of both ends. Its entity
+/// needs a `Handle<ParticleMaterial>`, [`Visibility`] and [`ComputedVisibility`] to be
+/// drawn. Curves are sampled along the beam, from 0.0 at the start to 1.0 at the end.
+#[derive(Component, Debug, Clone)]
+pub struct ParticleBeam {
+    pub start: Entity,
+    pub end: Entity,
+    pub segments: usize,
+    pub displacement: BeamDisplacement,
+    pub branching: Option<BeamBranching>,
+    /// Seconds between re-rolling jitter and branches. Zero re-rolls every frame.
+    pub refresh_interval: f32,
+    pub width: CurveFixed<f32>,
+    pub color: CurveFixed<Vec4>,
+    pub uv_mode: RibbonUvMode,
+    seed: u64,
+    elapsed: f32,
+    generation: u64,
+    strips: Vec<BeamStrip>,
+}
+
+#[derive(Debug, Clone)]
+struct BeamStrip {
+    points: Vec<Vec3>,
+    scale: f32,
+    depth: u32,
+}
+
+impl ParticleBeam {
+    pub fn new(start: Entity, end: Entity) -> Self {
+        Self {
+            start,
+            end,
+            segments: 16,
+            displacement: BeamDisplacement::None,
+            branching: None,
+            refresh_interval: 0.05,
+            width: curve::from_vec(vec![0.1]),
+            color: curve::from_vec(vec![Vec4::ONE]),
+            uv_mode: RibbonUvMode::Stretch,
+            seed: 0,
+            elapsed: 0.0,
+            generation: 0,
+            strips: Vec::new(),
+        }
+    }
+
+    pub fn with_segments(mut self, segments: usize) -> Self {
+        self.segments = segments;
+        self
+    }
+
+    pub fn with_jitter(mut self, amplitude: f32) -> Self {
+        self.displacement = BeamDisplacement::Jitter { amplitude };
+        self
+    }
+
+    pub fn with_noise(mut self, amplitude: f32, frequency: f32, speed: f32) -> Self {
+        self.displacement = BeamDisplacement::Noise {
+            amplitude,
+            frequency,
+            speed,
+        };
+        self
+    }
+
+    pub fn with_branching(mut self, branching: BeamBranching) -> Self {
+        self.branching = Some(branching);
+        self
+    }
+
+    /// Makes the beam's shape deterministic. Beams with the same seed and settings produce
+    /// the same points at the same time.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_refresh_interval(mut self, refresh_interval: f32) -> Self {
+        self.refresh_interval = refresh_interval;
+        self
+    }
+
+    pub fn with_width(mut self, width: CurveFixed<f32>) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn with_color(mut self, color: CurveFixed<Vec4>) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_uv_mode(mut self, uv_mode: RibbonUvMode) -> Self {
+        self.uv_mode = uv_mode;
+        self
+    }
+
+    /// Iterates over the points of the main beam and every branch, in world space.
+    pub fn strips(&self) -> impl Iterator<Item = &[Vec3]> {
+        self.strips.iter().map(|strip| strip.points.as_slice())
+    }
+
+    fn generate(&mut self, start: Vec3, end: Vec3) {
+        let mut rng = SmallRng::seed_from_u64(
+            self.seed ^ self.generation.wrapping_mul(0x9E37_79B9_7F4A_7C15),
+        );
+        self.strips.clear();
+        let points = self.displace(start, end, self.segments, 1.0, self.seed, &mut rng);
+        self.strips.push(BeamStrip {
+            points,
+            scale: 1.0,
+            depth: 0,
+        });
+
+        let branching = match self.branching.clone() {
+            Some(branching) => branching,
+            None => return,
+        };
+        let mut idx = 0;
+        while idx < self.strips.len() {
+            let strip = &self.strips[idx];
+            let length = strip.points[0].distance(strip.points[strip.points.len() - 1]);
+            if strip.depth < branching.depth && length > 0.0 {
+                let (scale, depth) = (strip.scale, strip.depth);
+                let points = strip.points.clone();
+                let axis = (points[points.len() - 1] - points[0]) / length;
+                // Branches never fork from either end of their parent.
+                for point in points[1..points.len() - 1].iter().copied() {
+                    if !rng.gen_bool(branching.chance.clamp(0.0, 1.0) as f64) {
+                        continue;
+                    }
+                    let direction = sample_cone(axis, branching.angle, &mut rng);
+                    let branch_length = if branching.length.end > branching.length.start {
+                        rng.gen_range(branching.length.clone())
+                    } else {
+                        branching.length.start
+                    };
+                    let scale = scale * branching.scale;
+                    let noise_seed = rng.gen();
+                    let points = self.displace(
+                        point,
+                        point + direction * branch_length * length,
+                        branching.segments,
+                        scale,
+                        noise_seed,
+                        &mut rng,
+                    );
+                    self.strips.push(BeamStrip {
+                        points,
+                        scale,
+                        depth: depth + 1,
+                    });
+                }
+            }
+            idx += 1;
+        }
+    }
+
+    /// Generates the points of a strip, tapering the displacement to zero at both ends.
+    fn displace(
+        &self,
+        start: Vec3,
+        end: Vec3,
+        segments: usize,
+        scale: f32,
+        noise_seed: u64,
+        rng: &mut impl Rng,
+    ) -> Vec<Vec3> {
+        let segments = segments.max(1);
+        let (side, up) = perpendicular_axes((end - start).normalize_or_zero());
+        (0..=segments)
+            .map(|idx| {
+                let t = idx as f32 / segments as f32;
+                let envelope = (t * PI).sin();
+                let offset = match self.displacement {
+                    BeamDisplacement::None => Vec2::ZERO,
+                    BeamDisplacement::Jitter { amplitude } => {
+                        Vec2::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0)) * amplitude
+                    }
+                    BeamDisplacement::Noise {
+                        amplitude,
+                        frequency,
+                        speed,
+                    } => {
+                        let x = t * frequency + self.elapsed * speed;
+                        Vec2::new(
+                            value_noise(noise_seed, x),
+                            value_noise(noise_seed.wrapping_add(1), x),
+                        ) * amplitude
+                    }
+                };
+                let offset = offset * envelope * scale;
+                start.lerp(end, t) + side * offset.x + up * offset.y
+            })
+            .collect()
+    }
+
+    /// Builds the ribbon strips for the beam, appending them to `geometry`.
+    pub(crate) fn build(&self, geometry: &mut RibbonGeometry) {
+        let mut points = Vec::new();
+        for strip in self.strips.iter() {
+            points.clear();
+            points.extend(strip.points.iter().map(|position| RibbonPoint {
+                position: *position,
+                size: strip.scale,
+                color: Vec4::ONE,
+            }));
+            ribbon::build_strip(&points, &self.width, &self.color, self.uv_mode, geometry);
+        }
+    }
+}
+
+/// Finds two axes perpendicular to `direction` and each other.
+fn perpendicular_axes(direction: Vec3) -> (Vec3, Vec3) {
+    if direction == Vec3::ZERO {
+        return (Vec3::X, Vec3::Z);
+    }
+    let reference = if direction.x.abs() < 0.9 {
+        Vec3::X
+    } else {
+        Vec3::Y
+    };
+    let side = direction.cross(reference).normalize();
+    (side, direction.cross(side))
+}
+
+/// Smooth 1D value noise in the range -1.0 to 1.0.
+fn value_noise(seed: u64, x: f32) -> f32 {
+    fn hash(seed: u64, cell: i64) -> f32 {
+        let mut value = seed ^ (cell as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        value ^= value >> 33;
+        value = value.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+        value ^= value >> 33;
+        (value >> 40) as f32 / (1u64 << 24) as f32 * 2.0 - 1.0
+    }
+    let cell = x.floor();
+    let t = x - cell;
+    let t = t * t * (3.0 - 2.0 * t);
+    let (a, b) = (hash(seed, cell as i64), hash(seed, cell as i64 + 1));
+    a + (b - a) * t
+}
+
+pub(crate) fn update_particle_beams(
+    time: Res<Time>,
+    transforms: Query<&GlobalTransform>,
+    mut beams: Query<&mut ParticleBeam>,
+) {
+    let delta_time = time.delta_seconds();
+    for mut beam in beams.iter_mut() {
+        let (start, end) = match (transforms.get(beam.start), transforms.get(beam.end)) {
+            (Ok(start), Ok(end)) => (start.translation, end.translation),
+            _ => {
+                beam.strips.clear();
+                continue;
+            }
+        };
+        beam.elapsed += delta_time;
+        if beam.refresh_interval > 0.0 {
+            beam.generation = (beam.elapsed / beam.refresh_interval) as u64;
+        } else {
+            beam.generation = beam.generation.wrapping_add(1);
+        }
+        beam.generate(start, end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn beam(seed: u64) -> ParticleBeam {
+        let entity = Entity::from_raw(0);
+        ParticleBeam::new(entity, entity)
+            .with_jitter(0.5)
+            .with_branching(BeamBranching {
+                chance: 0.5,
+                depth: 2,
+                ..Default::default()
+            })
+            .with_seed(seed)
+    }
+
+    fn generate(mut beam: ParticleBeam) -> Vec<Vec<Vec3>> {
+        beam.generate(Vec3::ZERO, Vec3::new(10.0, 0.0, 0.0));
+        beam.strips().map(|strip| strip.to_vec()).collect()
+    }
+
+    #[test]
+    fn same_seed_is_deterministic() {
+        let strips = generate(beam(7));
+        assert!(strips.len() > 1);
+        assert_eq!(strips, generate(beam(7)));
+    }
+
+    #[test]
+    fn different_seeds_differ() {
+        assert_ne!(generate(beam(7)), generate(beam(8)));
+    }
+
+    #[test]
+    fn different_generations_differ() {
+        let mut next = beam(7);
+        next.generation = 1;
+        assert_ne!(generate(beam(7)), generate(next));
+    }
+
+    #[test]
+    fn main_strip_connects_ends() {
+        let strips = generate(beam(7));
+        let main = &strips[0];
+        assert_eq!(main.len(), 17);
+        assert!(main[0].distance(Vec3::ZERO) < 1e-4);
+        assert!(main[main.len() - 1].distance(Vec3::new(10.0, 0.0, 0.0)) < 1e-4);
+    }
+
+    #[test]
+    fn branching_respects_depth() {
+        for depth in 0..3 {
+            let mut beam = beam(7);
+            beam.branching = Some(BeamBranching {
+                chance: 1.0,
+                depth,
+                ..Default::default()
+            });
+            beam.generate(Vec3::ZERO, Vec3::new(10.0, 0.0, 0.0));
+            let max_depth = beam.strips.iter().map(|strip| strip.depth).max().unwrap();
+            assert_eq!(max_depth, depth);
+        }
+    }
+}
@@ -0,0 +1,364 @@
+use bevy::{
+    math::*,
+    render::mesh::{Indices, Mesh, PrimitiveTopology, VertexAttributeValues},
+};
+use std::fmt;
+
+/// The maximum number of triangles stored in a single leaf of the BVH.
+const MAX_LEAF_TRIANGLES: usize = 4;
+
+/// The size of the traversal stack used when sweeping. Nodes are split at the median, so the
+/// tree is never deeper than `log2` of the triangle count, which is at most 32.
+const MAX_STACK_DEPTH: usize = 64;
+
+#[derive(Debug, Clone)]
+pub enum MeshColliderError {
+    /// Only triangle lists can be collided against.
+    UnsupportedTopology(PrimitiveTopology),
+    /// The mesh does not have a `Float32x3` position attribute.
+    MissingPositions,
+    /// An index refers to a vertex past the end of the position attribute.
+    IndexOutOfBounds { index: usize, vertex_count: usize },
+}
+
+impl fmt::Display for MeshColliderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedTopology(topology) => write!(
+                f,
+                "mesh colliders only support triangle lists, found {:?}",
+                topology
+            ),
+            Self::MissingPositions => {
+                write!(f, "mesh colliders require a Float32x3 position attribute")
+            }
+            Self::IndexOutOfBounds {
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "mesh index {} is out of bounds for {} vertices",
+                index, vertex_count
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MeshColliderError {}
+
+#[derive(Debug, Clone, Copy)]
+struct Triangle {
+    a: Vec3,
+    b: Vec3,
+    c: Vec3,
+}
+
+impl Triangle {
+    #[inline]
+    fn centroid(&self) -> Vec3 {
+        (self.a + self.b + self.c) / 3.0
+    }
+
+    /// Intersects a segment with the triangle using the Möller–Trumbore algorithm. Both
+    /// faces of the triangle are considered solid; the returned normal always faces against
+    /// the direction of travel.
+    fn sweep(&self, start: Vec3, delta: Vec3) -> Option<(f32, Vec3)> {
+        let edge_ab = self.b - self.a;
+        let edge_ac = self.c - self.a;
+        let p = delta.cross(edge_ac);
+        let det = edge_ab.dot(p);
+        if det.abs() <= f32::EPSILON {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+        let s = start - self.a;
+        let u = s.dot(p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(edge_ab);
+        let v = delta.dot(q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let time = edge_ac.dot(q) * inv_det;
+        if !(0.0..=1.0).contains(&time) {
+            return None;
+        }
+        let normal = edge_ab.cross(edge_ac).normalize_or_zero();
+        if normal.dot(delta) > 0.0 {
+            Some((time, -normal))
+        } else {
+            Some((time, normal))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BvhNode {
+    min: Vec3,
+    max: Vec3,
+    /// For leaves, the index of the first triangle. For interior nodes, the index of the
+    /// left child. The right child always immediately follows the left child.
+    start: u32,
+    /// The number of triangles in a leaf. Zero for interior nodes.
+    count: u32,
+}
+
+impl BvhNode {
+    /// Clips a segment against the node's bounds, returning true if any part of it within
+    /// `0..=max_time` overlaps the node.
+    #[inline]
+    fn intersects(&self, start: Vec3, inv_delta: Vec3, max_time: f32) -> bool {
+        let t0 = (self.min - start) * inv_delta;
+        let t1 = (self.max - start) * inv_delta;
+        let near = t0.min(t1).max_element().max(0.0);
+        let far = t0.max(t1).min_element().min(max_time);
+        near <= far
+    }
+}
+
+/// A bounding volume hierarchy over the triangles of a mesh, used for sweeping particles
+/// against arbitrary static geometry.
+#[derive(Debug, Clone)]
+pub struct TriangleBvh {
+    nodes: Vec<BvhNode>,
+    triangles: Vec<Triangle>,
+}
+
+impl TriangleBvh {
+    pub fn from_mesh(mesh: &Mesh) -> Result<Self, MeshColliderError> {
+        let topology = mesh.primitive_topology();
+        if topology != PrimitiveTopology::TriangleList {
+            return Err(MeshColliderError::UnsupportedTopology(topology));
+        }
+        let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float32x3(positions)) => positions,
+            _ => return Err(MeshColliderError::MissingPositions),
+        };
+        let indices: Vec<usize> = match mesh.indices() {
+            Some(Indices::U16(indices)) => indices.iter().map(|idx| *idx as usize).collect(),
+            Some(Indices::U32(indices)) => indices.iter().map(|idx| *idx as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+        let vertex = |index: usize| -> Result<Vec3, MeshColliderError> {
+            positions
+                .get(index)
+                .map(|position| Vec3::from(*position))
+                .ok_or(MeshColliderError::IndexOutOfBounds {
+                    index,
+                    vertex_count: positions.len(),
+                })
+        };
+        let triangles = indices
+            .chunks_exact(3)
+            .map(|tri| {
+                Ok(Triangle {
+                    a: vertex(tri[0])?,
+                    b: vertex(tri[1])?,
+                    c: vertex(tri[2])?,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self::build(triangles))
+    }
+
+    fn build(triangles: Vec<Triangle>) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(2 * triangles.len() / MAX_LEAF_TRIANGLES + 1),
+            triangles,
+        };
+        bvh.nodes.push(BvhNode {
+            min: Vec3::ZERO,
+            max: Vec3::ZERO,
+            start: 0,
+            count: bvh.triangles.len() as u32,
+        });
+        bvh.subdivide(0);
+        bvh
+    }
+
+    fn subdivide(&mut self, node_idx: usize) {
+        let BvhNode { start, count, .. } = self.nodes[node_idx];
+        let range = start as usize..(start + count) as usize;
+
+        let mut min = Vec3::splat(f32::MAX);
+        let mut max = Vec3::splat(f32::MIN);
+        let mut centroid_min = Vec3::splat(f32::MAX);
+        let mut centroid_max = Vec3::splat(f32::MIN);
+        for triangle in self.triangles[range.clone()].iter() {
+            min = min.min(triangle.a).min(triangle.b).min(triangle.c);
+            max = max.max(triangle.a).max(triangle.b).max(triangle.c);
+            centroid_min = centroid_min.min(triangle.centroid());
+            centroid_max = centroid_max.max(triangle.centroid());
+        }
+        self.nodes[node_idx].min = min;
+        self.nodes[node_idx].max = max;
+
+        if range.len() <= MAX_LEAF_TRIANGLES {
+            return;
+        }
+
+        // Split on the median centroid along the longest axis.
+        let extent = centroid_max - centroid_min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+        let mid = range.len() / 2;
+        self.triangles[range.clone()].select_nth_unstable_by(mid, |a, b| {
+            a.centroid()[axis]
+                .partial_cmp(&b.centroid()[axis])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let left = self.nodes.len();
+        self.nodes.push(BvhNode {
+            min: Vec3::ZERO,
+            max: Vec3::ZERO,
+            start,
+            count: mid as u32,
+        });
+        self.nodes.push(BvhNode {
+            min: Vec3::ZERO,
+            max: Vec3::ZERO,
+            start: start + mid as u32,
+            count: count - mid as u32,
+        });
+        self.nodes[node_idx].start = left as u32;
+        self.nodes[node_idx].count = 0;
+        self.subdivide(left);
+        self.subdivide(left + 1);
+    }
+
+    /// Sweeps a point along the segment from `start` to `end`, returning the fraction of the
+    /// segment travelled before the first hit and the surface normal.
+    pub fn sweep(&self, start: Vec3, end: Vec3) -> Option<(f32, Vec3)> {
+        if self.triangles.is_empty() {
+            return None;
+        }
+        let delta = end - start;
+        let inv_delta = delta.recip();
+        let mut closest: Option<(f32, Vec3)> = None;
+        let mut stack = [0usize; MAX_STACK_DEPTH];
+        let mut stack_len = 1;
+        while stack_len > 0 {
+            stack_len -= 1;
+            let node = &self.nodes[stack[stack_len]];
+            let max_time = closest.map_or(1.0, |(time, _)| time);
+            if !node.intersects(start, inv_delta, max_time) {
+                continue;
+            }
+            if node.count == 0 {
+                stack[stack_len] = node.start as usize;
+                stack[stack_len + 1] = node.start as usize + 1;
+                stack_len += 2;
+                continue;
+            }
+            let range = node.start as usize..(node.start + node.count) as usize;
+            for triangle in self.triangles[range].iter() {
+                if let Some((time, normal)) = triangle.sweep(start, delta) {
+                    if closest.map_or(true, |(closest, _)| time < closest) {
+                        closest = Some((time, normal));
+                    }
+                }
+            }
+        }
+        closest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A flat grid of `size` by `size` quads on the XZ plane, centered on the origin.
+    fn grid(size: u32) -> Mesh {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        let half = size as f32 / 2.0;
+        for z in 0..=size {
+            for x in 0..=size {
+                positions.push([x as f32 - half, 0.0, z as f32 - half]);
+            }
+        }
+        for z in 0..size {
+            for x in 0..size {
+                let idx = z * (size + 1) + x;
+                let next = idx + size + 1;
+                indices.extend_from_slice(&[idx, next, idx + 1, idx + 1, next, next + 1]);
+            }
+        }
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.set_indices(Some(Indices::U32(indices)));
+        mesh
+    }
+
+    #[test]
+    fn builds_hierarchy_over_all_triangles() {
+        let bvh = TriangleBvh::from_mesh(&grid(8)).unwrap();
+        assert_eq!(bvh.triangles.len(), 128);
+        assert!(bvh.nodes.len() > 1);
+        assert_eq!(bvh.nodes[0].min, Vec3::new(-4.0, 0.0, -4.0));
+        assert_eq!(bvh.nodes[0].max, Vec3::new(4.0, 0.0, 4.0));
+        let leaves: u32 = bvh.nodes.iter().map(|node| node.count).sum();
+        assert_eq!(leaves, 128);
+    }
+
+    #[test]
+    fn rejects_out_of_bounds_indices() {
+        let mut mesh = grid(1);
+        mesh.set_indices(Some(Indices::U16(vec![0, 1, 4])));
+        assert!(matches!(
+            TriangleBvh::from_mesh(&mesh),
+            Err(MeshColliderError::IndexOutOfBounds {
+                index: 4,
+                vertex_count: 4,
+            })
+        ));
+    }
+
+    #[test]
+    fn sweep_hits_closest_triangle() {
+        let bvh = TriangleBvh::from_mesh(&grid(8)).unwrap();
+        let (time, normal) = bvh
+            .sweep(Vec3::new(1.25, 2.0, -2.5), Vec3::new(1.25, -2.0, -2.5))
+            .unwrap();
+        assert!((time - 0.5).abs() < 1e-5);
+        assert_eq!(normal, Vec3::Y);
+
+        // The normal faces against the direction of travel from either side.
+        let (_, normal) = bvh
+            .sweep(Vec3::new(0.5, -1.0, 0.5), Vec3::new(0.5, 1.0, 0.5))
+            .unwrap();
+        assert_eq!(normal, -Vec3::Y);
+    }
+
+    #[test]
+    fn sweep_misses() {
+        let bvh = TriangleBvh::from_mesh(&grid(8)).unwrap();
+        // Ends before reaching the grid.
+        assert!(bvh
+            .sweep(Vec3::new(0.5, 2.0, 0.5), Vec3::new(0.5, 1.0, 0.5))
+            .is_none());
+        // Passes beside the grid.
+        assert!(bvh
+            .sweep(Vec3::new(5.0, 1.0, 0.5), Vec3::new(5.0, -1.0, 0.5))
+            .is_none());
+    }
+
+    #[test]
+    fn sweep_parallel_to_triangles() {
+        let bvh = TriangleBvh::from_mesh(&grid(8)).unwrap();
+        assert!(bvh
+            .sweep(Vec3::new(-5.0, 0.5, 0.25), Vec3::new(5.0, 0.5, 0.25))
+            .is_none());
+        assert!(bvh
+            .sweep(Vec3::new(-5.0, 0.0, 0.25), Vec3::new(5.0, 0.0, 0.25))
+            .is_none());
+    }
+}
@@ -0,0 +1,766 @@
+use crate::{bvh::TriangleBvh, heightfield::Heightfield, particles::ParticleLayers};
+use bevy::{
+    asset::{AssetEvent, Assets, Handle},
+    log::warn,
+    math::*,
+    prelude::*,
+    render::{mesh::Mesh, texture::Image},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+/// Offset applied along the surface normal after a collision to keep particles from
+/// immediately re-colliding with the same surface.
+const COLLISION_EPSILON: f32 = 1e-4;
+
+/// How a particle reacts after hitting a collider.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CollisionResponse {
+    /// The fraction of the velocity along the surface normal that is kept, reflected, after
+    /// a collision. 0.0 stops the particle dead, 1.0 is a perfectly elastic bounce.
+    pub bounce: f32,
+    /// The fraction of the velocity tangent to the surface that is lost after a collision.
+    pub friction: f32,
+    /// The fraction of a particle's total lifetime that is removed on each collision.
+    pub lifetime_loss: f32,
+    /// If true, particles are killed as soon as they collide.
+    pub kill: bool,
+}
+
+impl Default for CollisionResponse {
+    fn default() -> Self {
+        Self {
+            bounce: 0.5,
+            friction: 0.0,
+            lifetime_loss: 0.0,
+            kill: false,
+        }
+    }
+}
+
+impl CollisionResponse {
+    /// A response that kills particles on contact.
+    pub fn kill() -> Self {
+        Self {
+            kill: true,
+            ..Default::default()
+        }
+    }
+
+    /// Computes the velocity of a particle after colliding with a surface.
+    #[inline]
+    pub fn reflect(&self, velocity: Vec3, normal: Vec3) -> Vec3 {
+        let normal_velocity = normal * velocity.dot(normal);
+        let tangent_velocity = velocity - normal_velocity;
+        tangent_velocity * (1.0 - self.friction) - normal_velocity * self.bounce
+    }
+}
+
+/// Analytic collider shapes, defined in the collider's local space.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColliderShape {
+    /// An infinite plane through the origin with a normal of +Y. Particles only collide
+    /// when crossing from above to below the plane.
+    Plane,
+    /// A solid sphere centered on the origin.
+    Sphere { radius: f32 },
+    /// A solid box centered on the origin. Rotating the entity turns it into an oriented box.
+    Box { half_extents: Vec3 },
+    /// A solid capsule centered on the origin, with its segment running along the Y axis.
+    Capsule { radius: f32, half_height: f32 },
+}
+
+impl ColliderShape {
+    /// Sweeps a point along the segment from `start` to `end`, returning the fraction of the
+    /// segment travelled before the first hit and the local-space surface normal.
+    ///
+    /// Points that start inside the shape do not collide with it.
+    fn sweep(&self, start: Vec3, end: Vec3) -> Option<(f32, Vec3)> {
+        match self {
+            Self::Plane => sweep_plane(start, end),
+            Self::Sphere { radius } => sweep_sphere(Vec3::ZERO, *radius, start, end),
+            Self::Box { half_extents } => sweep_box(*half_extents, start, end),
+            Self::Capsule {
+                radius,
+                half_height,
+            } => sweep_capsule(*radius, *half_height, start, end),
+        }
+    }
+}
+
+/// A collider that particles test against as they move.
+///
+/// Colliders are positioned by the entity's [`GlobalTransform`] and only affect particle
+/// systems that share a layer with them.
+#[derive(Component, Debug, Clone)]
+pub struct ParticleCollider {
+    pub shape: ColliderShape,
+    pub response: CollisionResponse,
+    pub layers: ParticleLayers,
+}
+
+impl ParticleCollider {
+    pub fn plane() -> Self {
+        Self::from(ColliderShape::Plane)
+    }
+
+    pub fn sphere(radius: f32) -> Self {
+        Self::from(ColliderShape::Sphere { radius })
+    }
+
+    pub fn cuboid(half_extents: Vec3) -> Self {
+        Self::from(ColliderShape::Box { half_extents })
+    }
+
+    pub fn capsule(radius: f32, half_height: f32) -> Self {
+        Self::from(ColliderShape::Capsule {
+            radius,
+            half_height,
+        })
+    }
+
+    pub fn with_response(mut self, response: CollisionResponse) -> Self {
+        self.response = response;
+        self
+    }
+
+    pub fn with_layers(mut self, layers: ParticleLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+}
+
+impl From<ColliderShape> for ParticleCollider {
+    fn from(shape: ColliderShape) -> Self {
+        Self {
+            shape,
+            response: CollisionResponse::default(),
+            layers: ParticleLayers::all(),
+        }
+    }
+}
+
+/// A collider that uses the triangles of a [`Mesh`] asset.
+///
+/// The mesh must be a triangle list. A BVH is built for each mesh the first time it is
+/// used and shared between every collider using the same handle.
+#[derive(Component, Debug, Clone)]
+pub struct MeshCollider {
+    pub mesh: Handle<Mesh>,
+    pub response: CollisionResponse,
+    pub layers: ParticleLayers,
+}
+
+impl MeshCollider {
+    pub fn new(mesh: Handle<Mesh>) -> Self {
+        Self {
+            mesh,
+            response: CollisionResponse::default(),
+            layers: ParticleLayers::all(),
+        }
+    }
+
+    pub fn with_response(mut self, response: CollisionResponse) -> Self {
+        self.response = response;
+        self
+    }
+
+    pub fn with_layers(mut self, layers: ParticleLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+}
+
+/// A cache of the BVHs built for [`MeshCollider`]s, keyed by mesh handle.
+///
+/// Entries are rebuilt whenever the underlying mesh is modified. Meshes that fail to build
+/// are only retried once they are modified.
+#[derive(Default)]
+pub struct MeshColliderCache {
+    bvhs: HashMap<Handle<Mesh>, Arc<TriangleBvh>>,
+    failed: HashSet<Handle<Mesh>>,
+}
+
+impl MeshColliderCache {
+    pub fn get(&self, mesh: &Handle<Mesh>) -> Option<&Arc<TriangleBvh>> {
+        self.bvhs.get(mesh)
+    }
+}
+
+/// How particles behave after touching a heightfield.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeightfieldContact {
+    /// Bounce off of the surface using the collider's [`CollisionResponse`].
+    Bounce,
+    /// Stop moving entirely and stay on the surface.
+    Stick,
+    /// Lose all velocity into the surface and slide along it, subject to friction.
+    Slide,
+}
+
+#[derive(Debug, Clone)]
+pub enum HeightfieldSource {
+    /// A prebuilt heightfield.
+    Grid(Arc<Heightfield>),
+    /// A heightfield read from the first channel of an [`Image`] asset, spanning `size` in
+    /// the X and Z axes with heights scaled from `0.0..=1.0` to `0.0..=height_scale`.
+    Image {
+        image: Handle<Image>,
+        size: Vec2,
+        height_scale: f32,
+    },
+}
+
+/// A collider for terrain described by a [`Heightfield`].
+#[derive(Component, Debug, Clone)]
+pub struct HeightfieldCollider {
+    pub source: HeightfieldSource,
+    pub contact: HeightfieldContact,
+    pub response: CollisionResponse,
+    pub layers: ParticleLayers,
+}
+
+impl HeightfieldCollider {
+    pub fn from_heightfield(heightfield: Heightfield) -> Self {
+        Self::from(HeightfieldSource::Grid(Arc::new(heightfield)))
+    }
+
+    pub fn from_image(image: Handle<Image>, size: Vec2, height_scale: f32) -> Self {
+        Self::from(HeightfieldSource::Image {
+            image,
+            size,
+            height_scale,
+        })
+    }
+
+    pub fn with_contact(mut self, contact: HeightfieldContact) -> Self {
+        self.contact = contact;
+        self
+    }
+
+    pub fn with_response(mut self, response: CollisionResponse) -> Self {
+        self.response = response;
+        self
+    }
+
+    pub fn with_layers(mut self, layers: ParticleLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+}
+
+impl From<HeightfieldSource> for HeightfieldCollider {
+    fn from(source: HeightfieldSource) -> Self {
+        Self {
+            source,
+            contact: HeightfieldContact::Bounce,
+            response: CollisionResponse::default(),
+            layers: ParticleLayers::all(),
+        }
+    }
+}
+
+/// A cache of the heightfields read from images for [`HeightfieldCollider`]s, keyed by
+/// image handle.
+///
+/// Entries are rebuilt whenever the underlying image is modified. Images that fail to build
+/// are only retried once they are modified.
+#[derive(Default)]
+pub struct HeightfieldCache {
+    heightfields: HashMap<Handle<Image>, Arc<Heightfield>>,
+    failed: HashSet<Handle<Image>>,
+}
+
+impl HeightfieldCache {
+    pub fn get(&self, image: &Handle<Image>) -> Option<&Arc<Heightfield>> {
+        self.heightfields.get(image)
+    }
+}
+
+/// A single collision between a particle and a collider.
+#[derive(Debug, Clone)]
+pub struct ParticleCollision {
+    /// The entity of the collider that was hit.
+    pub collider: Entity,
+    /// The world-space point of contact.
+    pub position: Vec3,
+    /// The world-space surface normal at the point of contact.
+    pub normal: Vec3,
+    /// The particle's velocity before the collision.
+    pub velocity: Vec3,
+    /// If the particle was killed by the collision.
+    pub killed: bool,
+}
+
+/// Records the collisions that happened to a particle system during the last update.
+///
+/// Add this to a particle system's entity to observe its collisions. It is cleared at the
+/// start of every update.
+#[derive(Component, Debug, Clone, Default)]
+pub struct ParticleCollisions {
+    pub(crate) collisions: Vec<ParticleCollision>,
+}
+
+impl ParticleCollisions {
+    pub fn iter(&self) -> impl Iterator<Item = &ParticleCollision> {
+        self.collisions.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.collisions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.collisions.is_empty()
+    }
+}
+
+pub(crate) enum ColliderGeometry {
+    Shape(ColliderShape),
+    Mesh(Arc<TriangleBvh>),
+    Heightfield(Arc<Heightfield>, HeightfieldContact),
+}
+
+/// A collider with its transform resolved for the current frame.
+pub(crate) struct WorldCollider {
+    pub entity: Entity,
+    pub layers: ParticleLayers,
+    pub response: CollisionResponse,
+    world_to_local: Mat4,
+    geometry: ColliderGeometry,
+}
+
+impl WorldCollider {
+    pub fn new(
+        entity: Entity,
+        local_to_world: Mat4,
+        layers: ParticleLayers,
+        response: CollisionResponse,
+        geometry: ColliderGeometry,
+    ) -> Self {
+        Self {
+            entity,
+            layers,
+            response,
+            world_to_local: local_to_world.inverse(),
+            geometry,
+        }
+    }
+
+    /// Sweeps a world-space segment against the collider, returning the fraction of the
+    /// segment travelled and the world-space surface normal of the first hit.
+    fn sweep(&self, start: Vec3, end: Vec3) -> Option<(f32, Vec3)> {
+        let local_start = self.world_to_local.transform_point3(start);
+        let local_end = self.world_to_local.transform_point3(end);
+        let (time, normal) = match &self.geometry {
+            ColliderGeometry::Shape(shape) => shape.sweep(local_start, local_end)?,
+            ColliderGeometry::Mesh(bvh) => bvh.sweep(local_start, local_end)?,
+            ColliderGeometry::Heightfield(heightfield, _) => {
+                heightfield.sweep(local_start, local_end)?
+            }
+        };
+        // Normals transform by the inverse transpose to stay correct under non-uniform scale.
+        let normal = self
+            .world_to_local
+            .transpose()
+            .transform_vector3(normal)
+            .normalize_or_zero();
+        Some((time, normal))
+    }
+
+    /// Computes the velocity of a particle after hitting the collider.
+    fn resolve(&self, velocity: Vec3, normal: Vec3) -> Vec3 {
+        match self.geometry {
+            ColliderGeometry::Heightfield(_, HeightfieldContact::Stick) => Vec3::ZERO,
+            ColliderGeometry::Heightfield(_, HeightfieldContact::Slide) => {
+                let tangent_velocity = velocity - normal * velocity.dot(normal);
+                tangent_velocity * (1.0 - self.response.friction)
+            }
+            _ => self.response.reflect(velocity, normal),
+        }
+    }
+}
+
+/// All of the colliders in the world, gathered once per frame before particles are updated.
+#[derive(Default)]
+pub struct ParticleColliders {
+    pub(crate) colliders: Vec<WorldCollider>,
+}
+
+impl ParticleColliders {
+    pub(crate) fn filter(&self, layers: &ParticleLayers) -> Vec<&WorldCollider> {
+        self.colliders
+            .iter()
+            .filter(|collider| collider.layers.intersects(layers))
+            .collect()
+    }
+}
+
+/// The result of moving a particle through a set of colliders.
+pub(crate) struct CollisionResult {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub collider: Entity,
+    pub normal: Vec3,
+    pub response: CollisionResponse,
+}
+
+/// Continuously collides a particle moving from `start` to `end` with the earliest hit
+/// collider, if any.
+pub(crate) fn collide(
+    colliders: &[&WorldCollider],
+    start: Vec3,
+    end: Vec3,
+    velocity: Vec3,
+) -> Option<CollisionResult> {
+    let mut closest: Option<(f32, Vec3, &WorldCollider)> = None;
+    for collider in colliders {
+        if let Some((time, normal)) = collider.sweep(start, end) {
+            if closest.map_or(true, |(closest, _, _)| time < closest) {
+                closest = Some((time, normal, collider));
+            }
+        }
+    }
+    let (time, normal, collider) = closest?;
+    let contact = start.lerp(end, time);
+    Some(CollisionResult {
+        position: contact + normal * COLLISION_EPSILON,
+        velocity: collider.resolve(velocity, normal),
+        collider: collider.entity,
+        normal,
+        response: collider.response,
+    })
+}
+
+pub fn update_mesh_collider_cache(
+    mut events: EventReader<AssetEvent<Mesh>>,
+    meshes: Res<Assets<Mesh>>,
+    mut cache: ResMut<MeshColliderCache>,
+    colliders: Query<&MeshCollider>,
+) {
+    for event in events.iter() {
+        match event {
+            AssetEvent::Modified { handle } | AssetEvent::Removed { handle } => {
+                cache.bvhs.remove(handle);
+                cache.failed.remove(handle);
+            }
+            AssetEvent::Created { .. } => {}
+        }
+    }
+
+    for collider in colliders.iter() {
+        if cache.bvhs.contains_key(&collider.mesh) || cache.failed.contains(&collider.mesh) {
+            continue;
+        }
+        if let Some(mesh) = meshes.get(&collider.mesh) {
+            match TriangleBvh::from_mesh(mesh) {
+                Ok(bvh) => {
+                    cache.bvhs.insert(collider.mesh.clone_weak(), Arc::new(bvh));
+                }
+                Err(err) => {
+                    warn!("Failed to build mesh collider: {}", err);
+                    cache.failed.insert(collider.mesh.clone_weak());
+                }
+            }
+        }
+    }
+}
+
+pub fn update_heightfield_cache(
+    mut events: EventReader<AssetEvent<Image>>,
+    images: Res<Assets<Image>>,
+    mut cache: ResMut<HeightfieldCache>,
+    colliders: Query<&HeightfieldCollider>,
+) {
+    for event in events.iter() {
+        match event {
+            AssetEvent::Modified { handle } | AssetEvent::Removed { handle } => {
+                cache.heightfields.remove(handle);
+                cache.failed.remove(handle);
+            }
+            AssetEvent::Created { .. } => {}
+        }
+    }
+
+    for collider in colliders.iter() {
+        let handle = match &collider.source {
+            HeightfieldSource::Image { image, .. } => image,
+            HeightfieldSource::Grid(_) => continue,
+        };
+        if cache.heightfields.contains_key(handle) || cache.failed.contains(handle) {
+            continue;
+        }
+        if let Some(image) = images.get(handle) {
+            match Heightfield::from_image(image) {
+                Ok(heightfield) => {
+                    cache
+                        .heightfields
+                        .insert(handle.clone_weak(), Arc::new(heightfield));
+                }
+                Err(err) => {
+                    warn!("Failed to build heightfield collider: {}", err);
+                    cache.failed.insert(handle.clone_weak());
+                }
+            }
+        }
+    }
+}
+
+pub fn collect_particle_colliders(
+    mut colliders: ResMut<ParticleColliders>,
+    mesh_cache: Res<MeshColliderCache>,
+    heightfield_cache: Res<HeightfieldCache>,
+    shapes: Query<(Entity, &ParticleCollider, &GlobalTransform)>,
+    meshes: Query<(Entity, &MeshCollider, &GlobalTransform)>,
+    heightfields: Query<(Entity, &HeightfieldCollider, &GlobalTransform)>,
+) {
+    colliders.colliders.clear();
+    for (entity, collider, transform) in shapes.iter() {
+        colliders.colliders.push(WorldCollider::new(
+            entity,
+            transform.compute_matrix(),
+            collider.layers,
+            collider.response,
+            ColliderGeometry::Shape(collider.shape.clone()),
+        ));
+    }
+    for (entity, collider, transform) in meshes.iter() {
+        if let Some(bvh) = mesh_cache.get(&collider.mesh) {
+            colliders.colliders.push(WorldCollider::new(
+                entity,
+                transform.compute_matrix(),
+                collider.layers,
+                collider.response,
+                ColliderGeometry::Mesh(bvh.clone()),
+            ));
+        }
+    }
+    for (entity, collider, transform) in heightfields.iter() {
+        let (heightfield, local_to_world) = match &collider.source {
+            HeightfieldSource::Grid(heightfield) => {
+                (heightfield.clone(), transform.compute_matrix())
+            }
+            HeightfieldSource::Image {
+                image,
+                size,
+                height_scale,
+            } => match heightfield_cache.get(image) {
+                // Image heightfields are built at unit scale and stretched into place.
+                Some(heightfield) => (
+                    heightfield.clone(),
+                    transform.compute_matrix()
+                        * Mat4::from_scale(Vec3::new(size.x, *height_scale, size.y)),
+                ),
+                None => continue,
+            },
+        };
+        colliders.colliders.push(WorldCollider::new(
+            entity,
+            local_to_world,
+            collider.layers,
+            collider.response,
+            ColliderGeometry::Heightfield(heightfield, collider.contact),
+        ));
+    }
+}
+
+fn sweep_plane(start: Vec3, end: Vec3) -> Option<(f32, Vec3)> {
+    if start.y < 0.0 || end.y >= 0.0 {
+        return None;
+    }
+    Some((start.y / (start.y - end.y), Vec3::Y))
+}
+
+fn sweep_sphere(center: Vec3, radius: f32, start: Vec3, end: Vec3) -> Option<(f32, Vec3)> {
+    let offset = start - center;
+    let delta = end - start;
+    let c = offset.length_squared() - radius * radius;
+    if c < 0.0 {
+        return None;
+    }
+    let a = delta.length_squared();
+    let b = offset.dot(delta);
+    if a <= f32::EPSILON || b >= 0.0 {
+        return None;
+    }
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let time = (-b - discriminant.sqrt()) / a;
+    if !(0.0..=1.0).contains(&time) {
+        return None;
+    }
+    Some((time, (offset + delta * time) / radius))
+}
+
+fn sweep_box(half_extents: Vec3, start: Vec3, end: Vec3) -> Option<(f32, Vec3)> {
+    if start.abs().cmple(half_extents).all() {
+        return None;
+    }
+    let delta = end - start;
+    let mut enter = 0.0f32;
+    let mut exit = 1.0f32;
+    let mut normal = Vec3::ZERO;
+    for axis in 0..3 {
+        if delta[axis].abs() <= f32::EPSILON {
+            if start[axis].abs() > half_extents[axis] {
+                return None;
+            }
+            continue;
+        }
+        let inv = 1.0 / delta[axis];
+        let mut near = (-half_extents[axis] - start[axis]) * inv;
+        let mut far = (half_extents[axis] - start[axis]) * inv;
+        let mut sign = -1.0;
+        if near > far {
+            std::mem::swap(&mut near, &mut far);
+            sign = 1.0;
+        }
+        if near > enter {
+            enter = near;
+            normal = Vec3::ZERO;
+            normal[axis] = sign;
+        }
+        exit = exit.min(far);
+        if enter > exit {
+            return None;
+        }
+    }
+    if normal == Vec3::ZERO {
+        return None;
+    }
+    Some((enter, normal))
+}
+
+fn sweep_capsule(radius: f32, half_height: f32, start: Vec3, end: Vec3) -> Option<(f32, Vec3)> {
+    let closest_on_axis = Vec3::new(0.0, start.y.clamp(-half_height, half_height), 0.0);
+    if start.distance_squared(closest_on_axis) < radius * radius {
+        return None;
+    }
+
+    let delta = end - start;
+    let mut closest: Option<(f32, Vec3)> = None;
+
+    // The cylindrical body.
+    let a = delta.x * delta.x + delta.z * delta.z;
+    if a > f32::EPSILON {
+        let b = start.x * delta.x + start.z * delta.z;
+        let c = start.x * start.x + start.z * start.z - radius * radius;
+        let discriminant = b * b - a * c;
+        if discriminant >= 0.0 {
+            let time = (-b - discriminant.sqrt()) / a;
+            let point = start + delta * time;
+            if (0.0..=1.0).contains(&time) && point.y.abs() <= half_height {
+                closest = Some((time, Vec3::new(point.x, 0.0, point.z) / radius));
+            }
+        }
+    }
+
+    // The hemispherical caps.
+    for cap in [half_height, -half_height] {
+        if let Some((time, normal)) = sweep_sphere(Vec3::new(0.0, cap, 0.0), radius, start, end) {
+            if closest.map_or(true, |(closest, _)| time < closest) {
+                closest = Some((time, normal));
+            }
+        }
+    }
+
+    closest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collider(local_to_world: Mat4, shape: ColliderShape) -> WorldCollider {
+        WorldCollider::new(
+            Entity::from_raw(0),
+            local_to_world,
+            ParticleLayers::default(),
+            CollisionResponse::default(),
+            ColliderGeometry::Shape(shape),
+        )
+    }
+
+    #[test]
+    fn fast_particles_hit_thin_boxes() {
+        let shape = ColliderShape::Box {
+            half_extents: Vec3::new(1.0, 0.01, 1.0),
+        };
+        // The whole box lies between the start and end of the segment.
+        let (time, normal) = shape
+            .sweep(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, -10.0, 0.0))
+            .unwrap();
+        assert!((time - 0.4995).abs() < 1e-5);
+        assert_eq!(normal, Vec3::Y);
+
+        let (time, normal) = shape
+            .sweep(Vec3::new(0.5, -10.0, 0.5), Vec3::new(0.5, 10.0, 0.5))
+            .unwrap();
+        assert!((time - 0.4995).abs() < 1e-5);
+        assert_eq!(normal, -Vec3::Y);
+
+        assert!(shape
+            .sweep(Vec3::new(2.0, 10.0, 0.0), Vec3::new(2.0, -10.0, 0.0))
+            .is_none());
+    }
+
+    #[test]
+    fn fast_particles_hit_small_spheres() {
+        let shape = ColliderShape::Sphere { radius: 0.1 };
+        let (time, normal) = shape
+            .sweep(Vec3::new(-10.0, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0))
+            .unwrap();
+        assert!((time - 0.495).abs() < 1e-5);
+        assert!(normal.abs_diff_eq(-Vec3::X, 1e-4));
+
+        assert!(shape
+            .sweep(Vec3::new(-10.0, 0.2, 0.0), Vec3::new(10.0, 0.2, 0.0))
+            .is_none());
+    }
+
+    #[test]
+    fn hit_normals_face_the_particle() {
+        let (_, normal) = ColliderShape::Plane
+            .sweep(Vec3::new(3.0, 1.0, 0.0), Vec3::new(4.0, -1.0, 0.0))
+            .unwrap();
+        assert_eq!(normal, Vec3::Y);
+
+        let capsule = ColliderShape::Capsule {
+            radius: 0.5,
+            half_height: 1.0,
+        };
+        let (time, normal) = capsule
+            .sweep(Vec3::new(0.0, 0.5, 5.0), Vec3::new(0.0, 0.5, -5.0))
+            .unwrap();
+        assert!((time - 0.45).abs() < 1e-5);
+        assert!(normal.abs_diff_eq(Vec3::Z, 1e-5));
+        let (_, normal) = capsule
+            .sweep(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -5.0, 0.0))
+            .unwrap();
+        assert!(normal.abs_diff_eq(Vec3::Y, 1e-5));
+    }
+
+    #[test]
+    fn collide_transforms_hits_into_world_space() {
+        // A thin box turned on its side into a wall facing -X, just past the origin.
+        let wall = collider(
+            Mat4::from_rotation_translation(
+                Quat::from_rotation_z(std::f32::consts::FRAC_PI_2),
+                Vec3::new(1.0, 0.0, 0.0),
+            ),
+            ColliderShape::Box {
+                half_extents: Vec3::new(1.0, 0.01, 1.0),
+            },
+        );
+        let velocity = Vec3::new(100.0, 0.0, 0.0);
+        let hit = collide(&[&wall], Vec3::ZERO, Vec3::new(10.0, 0.0, 0.0), velocity).unwrap();
+        assert!(hit.normal.abs_diff_eq(-Vec3::X, 1e-5));
+        assert!(hit.position.abs_diff_eq(Vec3::new(0.99, 0.0, 0.0), 1e-3));
+        assert!(hit.position.x < 0.99);
+        assert!(hit.velocity.abs_diff_eq(Vec3::new(-50.0, 0.0, 0.0), 1e-3));
+    }
+}
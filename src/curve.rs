@@ -1,4 +1,7 @@
-use bevy::math::{curves::CurveFixed, interpolation::Lerp};
+use bevy::math::{
+    curves::{Curve, CurveFixed},
+    interpolation::Lerp,
+};
 use std::ops::Range;
 
 #[inline]
@@ -25,3 +28,37 @@ pub fn from_constant_vec<T: Lerp + Clone>(keyframes: Vec<T>) -> CurveFixed<Range
 pub fn from_vec<T: Lerp>(keyframes: Vec<T>) -> CurveFixed<T> {
     CurveFixed::from_keyframes(keyframes.len() as f32, 0, keyframes)
 }
+
+/// A value that can vary between particles and over the course of an emitter's duration.
+///
+/// Curves are sampled by the emitter's progress through its duration, from 0.0 to 1.0.
+#[derive(Debug, Clone)]
+pub enum ParticleValue<T: Lerp> {
+    Constant(T),
+    /// A random value between the start and end of the range.
+    Range(Range<T>),
+    /// A value, or gradient for colors, that changes over the emitter's duration.
+    Curve(CurveFixed<T>),
+    /// A random value between two curves over the emitter's duration.
+    RandomCurve(CurveFixed<Range<T>>),
+}
+
+impl<T> ParticleValue<T>
+where
+    T: Lerp + Clone,
+    Range<T>: Lerp + Clone,
+{
+    /// Samples the value at `progress` through the emitter's duration, using `random`, from
+    /// 0.0 to 1.0, to pick within ranges.
+    pub fn sample(&self, progress: f32, random: f32) -> T {
+        match self {
+            Self::Constant(value) => value.clone(),
+            Self::Range(range) => T::lerp_unclamped(&range.start, &range.end, random),
+            Self::Curve(curve) => curve.sample(progress),
+            Self::RandomCurve(curve) => {
+                let range = curve.sample(progress);
+                T::lerp_unclamped(&range.start, &range.end, random)
+            }
+        }
+    }
+}
@@ -1,30 +1,176 @@
-use crate::particles::{ParticleParams, Particles};
-use bevy::{math::*, prelude::*, tasks::ComputeTaskPool};
-use rand::Rng;
+use crate::{
+    curve::ParticleValue,
+    particles::{ParticleParams, Particles},
+    sampler::ImageWeight,
+    shape::{
+        sample_cone, EmissionDirection, EmissionMode, EmitterShape, EmitterShapeCache,
+        MeshColorSource, MeshSampleMode, RadiusDistribution, ShapeEmission,
+    },
+};
+use bevy::{ecs::system::Command, math::*, prelude::*, tasks::ComputeTaskPool};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
 use std::{ops::Range, time::Duration};
 
+/// A group of particles emitted at once.
+///
+/// Bursts fire in order. Each burst fires `cycles` times, `interval` apart, and the next
+/// burst fires `wait` after its last cycle. The list repeats until the emitter's duration
+/// ends, and restarts from the first burst at the start of every loop. If the last burst has
+/// no wait, the list repeats `interval` after its last cycle. A list that takes no time to
+/// fire, like a single burst with no wait or interval, fires once per loop.
 #[derive(Debug, Clone)]
 pub struct EmitterBurst {
     pub count: Range<usize>,
     pub wait: Duration,
+    /// How many times the burst fires before moving on. Zero is treated as one.
+    pub cycles: u32,
+    /// The time between cycles of the burst.
+    pub interval: Duration,
 }
 
+impl Default for EmitterBurst {
+    fn default() -> Self {
+        Self {
+            count: 1..2,
+            wait: Duration::ZERO,
+            cycles: 1,
+            interval: Duration::ZERO,
+        }
+    }
+}
+
+impl EmitterBurst {
+    fn sample_count(&self, rng: &mut impl Rng) -> usize {
+        if self.count.is_empty() {
+            self.count.start
+        } else {
+            rng.gen_range(self.count.clone())
+        }
+    }
+}
+
+/// The playback state of a [`ParticleEmitter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmitterState {
+    Playing,
+    Paused,
+    /// Stopped, either explicitly or by reaching the end of a non-looping duration. The
+    /// emitter stays stopped until it is played or restarted.
+    Stopped,
+}
+
+/// Information about the particle being emitted, passed to [`EmitterModifier`]s.
+pub struct EmitterContext<'a> {
+    /// The index of the particle within the group emitted together.
+    pub index: usize,
+    /// The number of particles emitted together. For bursts this is the burst's size.
+    pub count: usize,
+    /// The index of the burst emitting the particle, or `None` if it was emitted by a rate
+    /// or an [`EmitParticles`] request.
+    pub burst: Option<usize>,
+    /// The time, in seconds, that the emitter has been emitting since it last started.
+    pub elapsed: f32,
+    /// The transform particles are emitted from. For [`EmitParticles`] requests this is the
+    /// emitter's transform moved to the requested position.
+    pub transform: &'a GlobalTransform,
+    /// The emitter's random number generator, which is deterministic if the emitter was
+    /// built with a seed.
+    pub rng: &'a mut SmallRng,
+}
+
+/// Modifies the parameters of each particle as it is emitted, after it has been moved into
+/// world space.
 pub trait EmitterModifier: Send + Sync + 'static {
-    fn modify(&mut self, particle: &mut ParticleParams);
+    fn modify(&mut self, particle: &mut ParticleParams, ctx: &mut EmitterContext);
+}
+
+/// An [`EmitterModifier`] backed by a closure.
+pub struct FnEmitterModifier<F>(pub F);
+
+impl<F> EmitterModifier for FnEmitterModifier<F>
+where
+    F: FnMut(&mut ParticleParams, &mut EmitterContext) + Send + Sync + 'static,
+{
+    fn modify(&mut self, particle: &mut ParticleParams, ctx: &mut EmitterContext) {
+        (self.0)(particle, ctx);
+    }
+}
+
+/// A group of particles emitted together on one update.
+struct SpawnGroup {
+    burst: Option<usize>,
+    count: usize,
+    /// For particles emitted over distance, how far the emitter travelled since the last
+    /// update.
+    travel: Option<Vec3>,
+}
+
+/// A continuous rate of emission. Curves are sampled over the emitter's duration, where
+/// 0.0 is the start and 1.0 is the end of the duration.
+#[derive(Debug, Clone)]
+pub struct EmissionRate {
+    pub rate: ParticleValue<f32>,
+    /// The fractional particle carried over from previous updates.
+    accumulated: f32,
+}
+
+impl EmissionRate {
+    pub fn new(rate: ParticleValue<f32>) -> Self {
+        Self {
+            rate,
+            accumulated: 0.0,
+        }
+    }
+
+    pub fn constant(rate: f32) -> Self {
+        Self::new(ParticleValue::Constant(rate))
+    }
+
+    /// Accumulates `amount` scaled by the rate at `progress` through the emitter's duration,
+    /// returning the number of whole particles to emit.
+    fn accumulate(&mut self, progress: f32, amount: f32, rng: &mut impl Rng) -> usize {
+        self.accumulated += self.rate.sample(progress, rng.gen()).max(0.0) * amount;
+        let count = self.accumulated.floor();
+        self.accumulated -= count;
+        count as usize
+    }
 }
 
 #[derive(Component)]
 pub struct ParticleEmitter {
+    state: EmitterState,
+    clear_requested: bool,
     next_burst: Duration,
     burst_idx: usize,
+    burst_cycle: u32,
+    duration: Duration,
+    looping: bool,
+    start_delay: Duration,
+    delay_remaining: Duration,
+    elapsed: Duration,
+    age: Duration,
+    seed: Option<u64>,
+    rng: SmallRng,
+    rate_over_time: Option<EmissionRate>,
+    rate_over_distance: Option<EmissionRate>,
+    last_position: Option<Vec3>,
     default_params: ParticleParams,
-    default_speed: f32,
+    start_color: ParticleValue<Vec4>,
+    start_lifetime: ParticleValue<f32>,
+    start_size: ParticleValue<f32>,
+    start_speed: ParticleValue<f32>,
     bursts: Vec<EmitterBurst>,
     shape: EmitterShape,
+    emission: ShapeEmission,
     modifiers: Vec<Box<dyn EmitterModifier>>,
+    pending: Vec<EmitParticles>,
 }
 
 impl ParticleEmitter {
+    pub fn from_shape(shape: EmitterShape) -> ParticleEmitterBuilder {
+        ParticleEmitterBuilder::new(shape)
+    }
+
     pub fn sphere(center: Vec3, radius: f32) -> ParticleEmitterBuilder {
         ParticleEmitterBuilder::new(EmitterShape::Sphere { center, radius })
     }
@@ -32,29 +178,470 @@ impl ParticleEmitter {
     pub fn hemisphere(center: Vec3, radius: f32) -> ParticleEmitterBuilder {
         ParticleEmitterBuilder::new(EmitterShape::Hemisphere { center, radius })
     }
+
+    pub fn cuboid(center: Vec3, half_extents: Vec3) -> ParticleEmitterBuilder {
+        ParticleEmitterBuilder::new(EmitterShape::Box {
+            center,
+            half_extents,
+        })
+    }
+
+    /// Creates an emitter shaped like a cone opening along +Y. `angle` is in radians.
+    pub fn cone(center: Vec3, angle: f32, radius: f32, length: f32) -> ParticleEmitterBuilder {
+        ParticleEmitterBuilder::new(EmitterShape::Cone {
+            center,
+            angle,
+            radius,
+            length,
+        })
+    }
+
+    /// Creates an emitter that emits from the edge of a circle in the XZ plane.
+    pub fn circle(center: Vec3, radius: f32) -> ParticleEmitterBuilder {
+        ParticleEmitterBuilder::new(EmitterShape::Circle {
+            center,
+            radius,
+            arc: 0.0..std::f32::consts::TAU,
+        })
+        .with_emission_mode(EmissionMode::Shell)
+    }
+
+    /// Creates an emitter that emits from the area of a disc in the XZ plane.
+    pub fn disc(center: Vec3, radius: f32) -> ParticleEmitterBuilder {
+        ParticleEmitterBuilder::new(EmitterShape::Circle {
+            center,
+            radius,
+            arc: 0.0..std::f32::consts::TAU,
+        })
+    }
+
+    pub fn line(start: Vec3, end: Vec3) -> ParticleEmitterBuilder {
+        ParticleEmitterBuilder::new(EmitterShape::Line { start, end })
+    }
+
+    pub fn torus(center: Vec3, radius: f32, tube_radius: f32) -> ParticleEmitterBuilder {
+        ParticleEmitterBuilder::new(EmitterShape::Torus {
+            center,
+            radius,
+            tube_radius,
+            arc: 0.0..std::f32::consts::TAU,
+        })
+    }
+
+    /// Creates an emitter that emits from a mesh's surface, vertices or edges.
+    pub fn mesh(mesh: Handle<Mesh>, mode: MeshSampleMode) -> ParticleEmitterBuilder {
+        ParticleEmitterBuilder::new(EmitterShape::Mesh {
+            mesh,
+            mode,
+            color: MeshColorSource::None,
+        })
+    }
+
+    /// Creates an emitter that emits from the texels of an image, spread over a quad of the
+    /// given size in the XZ plane.
+    pub fn image(image: Handle<Image>, weight: ImageWeight, size: Vec2) -> ParticleEmitterBuilder {
+        ParticleEmitterBuilder::new(EmitterShape::Image {
+            image,
+            weight,
+            size,
+            use_color: false,
+        })
+    }
+
+    pub fn shape(&self) -> &EmitterShape {
+        &self.shape
+    }
+
+    pub fn state(&self) -> EmitterState {
+        self.state
+    }
+
+    /// Whether the emitter has stopped, either explicitly or because its duration ended
+    /// without looping. Particles it already emitted may still be alive.
+    pub fn is_finished(&self) -> bool {
+        self.state == EmitterState::Stopped
+    }
+
+    /// Resumes a paused emitter, or restarts a stopped one.
+    pub fn play(&mut self) {
+        match self.state {
+            EmitterState::Playing => {}
+            EmitterState::Paused => self.state = EmitterState::Playing,
+            EmitterState::Stopped => self.restart(),
+        }
+    }
+
+    /// Pauses emission, keeping the emitter's progress. Emitted particles keep simulating.
+    pub fn pause(&mut self) {
+        if self.state == EmitterState::Playing {
+            self.state = EmitterState::Paused;
+        }
+    }
+
+    /// Stops emitting. Emitted particles keep simulating until they die.
+    pub fn stop(&mut self) {
+        self.state = EmitterState::Stopped;
+    }
+
+    /// Stops emitting and removes every particle that was already emitted.
+    pub fn stop_and_clear(&mut self) {
+        self.stop();
+        self.clear_requested = true;
+    }
+
+    /// Restarts the emitter from the beginning of its duration, including its start delay.
+    /// A pending [`ParticleEmitter::stop_and_clear`] is cancelled.
+    pub fn restart(&mut self) {
+        self.state = EmitterState::Playing;
+        self.clear_requested = false;
+        self.delay_remaining = self.start_delay;
+        self.elapsed = Duration::ZERO;
+        self.age = Duration::ZERO;
+        self.last_position = None;
+        if let Some(seed) = self.seed {
+            self.rng = SmallRng::seed_from_u64(seed);
+        }
+        self.reset_bursts();
+        for rate in [&mut self.rate_over_time, &mut self.rate_over_distance]
+            .into_iter()
+            .flatten()
+        {
+            rate.accumulated = 0.0;
+        }
+    }
+
+    fn reset_bursts(&mut self) {
+        self.next_burst = Duration::ZERO;
+        self.burst_idx = 0;
+        self.burst_cycle = 0;
+    }
+
+    /// How far through its duration the emitter is, from 0.0 to 1.0.
+    pub fn progress(&self) -> f32 {
+        if self.duration.is_zero() {
+            return 0.0;
+        }
+        (self.elapsed.as_secs_f32() / self.duration.as_secs_f32()).clamp(0.0, 1.0)
+    }
+
+    /// Emits the particles due over the next `delta_time`.
+    fn emit(
+        &mut self,
+        particles: &mut Particles,
+        transform: &GlobalTransform,
+        delta_time: Duration,
+        shape_cache: &EmitterShapeCache,
+        rng: &mut SmallRng,
+    ) {
+        let mut groups = Vec::new();
+        let progress = self.advance(delta_time, rng, &mut groups);
+
+        let position = transform.translation;
+        let travel = position - self.last_position.unwrap_or(position);
+        self.last_position = Some(position);
+        let delaying = !self.delay_remaining.is_zero();
+        let distance_count = match self.rate_over_distance.as_mut() {
+            Some(rate) if !delaying => rate.accumulate(progress, travel.length(), rng),
+            _ => 0,
+        };
+        if distance_count > 0 {
+            groups.push(SpawnGroup {
+                burst: None,
+                count: distance_count,
+                travel: Some(travel),
+            });
+        }
+
+        let total: usize = groups.iter().map(|group| group.count).sum();
+        if total == 0 {
+            return;
+        }
+        particles.reserve(particles.len() + total);
+        let local_to_world = transform.compute_matrix();
+        let elapsed = self.age.as_secs_f32();
+        for group in groups {
+            for index in 0..group.count {
+                let mut params = self.sample_params(shape_cache, rng);
+                let speed = self.sample_speed(rng);
+                self.finish_params(&mut params, &local_to_world, speed);
+                if let Some(travel) = group.travel {
+                    // Spread particles emitted over distance evenly along the path the
+                    // emitter travelled since the last update.
+                    let t = (index + 1) as f32 / group.count as f32;
+                    params.position -= travel * (1.0 - t);
+                }
+                let mut ctx = EmitterContext {
+                    index,
+                    count: group.count,
+                    burst: group.burst,
+                    elapsed,
+                    transform,
+                    rng: &mut *rng,
+                };
+                self.spawn(particles, params, &mut ctx);
+            }
+        }
+    }
+
+    /// Advances the emitter's lifecycle, adding the groups of particles emitted by bursts
+    /// and the rate over time, and returning the progress through the duration at which
+    /// they were emitted.
+    fn advance(
+        &mut self,
+        delta_time: Duration,
+        rng: &mut impl Rng,
+        groups: &mut Vec<SpawnGroup>,
+    ) -> f32 {
+        let mut remaining = delta_time;
+        if !self.delay_remaining.is_zero() {
+            if remaining <= self.delay_remaining {
+                self.delay_remaining -= remaining;
+                return 0.0;
+            }
+            remaining -= self.delay_remaining;
+            self.delay_remaining = Duration::ZERO;
+        }
+
+        // A zero duration is treated as endless when looping.
+        let endless = self.duration.is_zero() && self.looping;
+        let progress = self.progress();
+        let mut emitting = Duration::ZERO;
+        loop {
+            let step = if endless {
+                remaining
+            } else {
+                remaining.min(self.duration.saturating_sub(self.elapsed))
+            };
+            self.fire_bursts(step, rng, groups);
+            self.elapsed += step;
+            remaining -= step;
+            emitting += step;
+            if endless || self.elapsed < self.duration {
+                break;
+            }
+            if !self.looping {
+                self.state = EmitterState::Stopped;
+                break;
+            }
+            self.elapsed = Duration::ZERO;
+            self.reset_bursts();
+            if remaining.is_zero() {
+                break;
+            }
+        }
+
+        self.age += emitting;
+
+        if let Some(rate) = self.rate_over_time.as_mut() {
+            let count = rate.accumulate(progress, emitting.as_secs_f32(), rng);
+            if count > 0 {
+                groups.push(SpawnGroup {
+                    burst: None,
+                    count,
+                    travel: None,
+                });
+            }
+        }
+        progress
+    }
+
+    /// Samples the parameters of a new particle from the emitter's start values and shape,
+    /// in local space. The velocity is left as the unit direction given by the shape.
+    fn sample_params(&self, shape_cache: &EmitterShapeCache, rng: &mut impl Rng) -> ParticleParams {
+        let progress = self.progress();
+        let mut params = self.default_params.clone();
+        params.color = Color::from(self.start_color.sample(progress, rng.gen()));
+        params.lifetime = self.start_lifetime.sample(progress, rng.gen());
+        params.size = self.start_size.sample(progress, rng.gen());
+        let sample = self.shape.sample(&self.emission, shape_cache, rng);
+        params.position = sample.position;
+        params.velocity = self.emission.sample_direction(&sample, rng);
+        if let Some(color) = sample.color {
+            params.color = color;
+        }
+        params
+    }
+
+    /// Transforms a particle sampled in local space into world space and sets its speed.
+    fn finish_params(&self, params: &mut ParticleParams, local_to_world: &Mat4, speed: f32) {
+        params.position = local_to_world.transform_point3(params.position);
+        params.velocity = local_to_world.transform_vector3(params.velocity);
+        if self.emission.align_rotation {
+            params.rotation = align_rotation(params.velocity);
+        }
+        params.velocity *= speed;
+    }
+
+    fn sample_speed(&self, rng: &mut impl Rng) -> f32 {
+        self.start_speed.sample(self.progress(), rng.gen())
+    }
+
+    fn spawn(
+        &mut self,
+        particles: &mut Particles,
+        mut params: ParticleParams,
+        ctx: &mut EmitterContext,
+    ) {
+        for modifier in self.modifiers.iter_mut() {
+            modifier.modify(&mut params, ctx);
+        }
+        particles.spawn(params);
+    }
+
+    /// Emits every queued [`EmitParticles`] request.
+    fn emit_pending(
+        &mut self,
+        particles: &mut Particles,
+        transform: &GlobalTransform,
+        shape_cache: &EmitterShapeCache,
+        rng: &mut SmallRng,
+    ) {
+        let local_to_world = transform.compute_matrix();
+        let elapsed = self.age.as_secs_f32();
+        for request in std::mem::take(&mut self.pending) {
+            let origin = GlobalTransform {
+                translation: request.position,
+                ..*transform
+            };
+            let count = if request.count.is_empty() {
+                request.count.start
+            } else {
+                rng.gen_range(request.count.clone())
+            };
+            particles.reserve(particles.len() + count);
+            for index in 0..count {
+                let mut params = self.sample_params(shape_cache, rng);
+                let speed = match request.overrides.speed {
+                    Some(speed) => speed,
+                    None => self.sample_speed(rng),
+                };
+                params.position =
+                    request.position + local_to_world.transform_vector3(params.position);
+                params.velocity = match request.direction.map(Vec3::normalize_or_zero) {
+                    Some(direction) if direction != Vec3::ZERO => {
+                        sample_cone(direction, request.cone_angle, rng)
+                    }
+                    _ => local_to_world.transform_vector3(params.velocity),
+                };
+                if self.emission.align_rotation {
+                    params.rotation = align_rotation(params.velocity);
+                }
+                params.velocity *= speed;
+                if let Some(color) = request.overrides.color {
+                    params.color = color;
+                }
+                if let Some(size) = request.overrides.size {
+                    params.size = size;
+                }
+                if let Some(lifetime) = request.overrides.lifetime {
+                    params.lifetime = lifetime;
+                }
+                let mut ctx = EmitterContext {
+                    index,
+                    count,
+                    burst: None,
+                    elapsed,
+                    transform: &origin,
+                    rng: &mut *rng,
+                };
+                self.spawn(particles, params, &mut ctx);
+            }
+        }
+    }
+
+    /// Fires every burst due within the next `remaining` time.
+    fn fire_bursts(
+        &mut self,
+        mut remaining: Duration,
+        rng: &mut impl Rng,
+        groups: &mut Vec<SpawnGroup>,
+    ) {
+        if self.bursts.is_empty() {
+            return;
+        }
+        let pass_duration = self.burst_pass_duration();
+        while remaining >= self.next_burst {
+            let burst = &self.bursts[self.burst_idx];
+            groups.push(SpawnGroup {
+                burst: Some(self.burst_idx),
+                count: burst.sample_count(rng),
+                travel: None,
+            });
+            remaining -= self.next_burst;
+
+            self.burst_cycle += 1;
+            if self.burst_cycle < burst.cycles.max(1) {
+                self.next_burst = burst.interval;
+                continue;
+            }
+            self.next_burst = burst.wait;
+            self.burst_cycle = 0;
+            self.burst_idx = (self.burst_idx + 1) % self.bursts.len();
+            if self.burst_idx == 0 {
+                if self.next_burst.is_zero() {
+                    self.next_burst = burst.interval;
+                }
+                if pass_duration.is_zero() {
+                    // Nothing is left to fire until the bursts are reset on the next loop.
+                    self.next_burst = Duration::MAX;
+                }
+            }
+        }
+        self.next_burst = self.next_burst.saturating_sub(remaining);
+    }
+
+    /// The time between the first burst firing and the list of bursts repeating.
+    fn burst_pass_duration(&self) -> Duration {
+        let mut duration: Duration = self
+            .bursts
+            .iter()
+            .map(|burst| burst.interval * (burst.cycles.max(1) - 1) + burst.wait)
+            .sum();
+        if let Some(last) = self.bursts.last().filter(|last| last.wait.is_zero()) {
+            duration += last.interval;
+        }
+        duration
+    }
 }
 
 pub struct ParticleEmitterBuilder {
+    duration: Duration,
+    looping: bool,
+    start_delay: Duration,
+    autoplay: bool,
+    rate_over_time: Option<EmissionRate>,
+    rate_over_distance: Option<EmissionRate>,
     default_params: ParticleParams,
-    default_speed: f32,
+    start_color: ParticleValue<Vec4>,
+    start_lifetime: ParticleValue<f32>,
+    start_size: ParticleValue<f32>,
+    start_speed: ParticleValue<f32>,
     bursts: Vec<EmitterBurst>,
     shape: EmitterShape,
+    emission: ShapeEmission,
     modifiers: Vec<Box<dyn EmitterModifier>>,
+    seed: Option<u64>,
 }
 
 impl ParticleEmitterBuilder {
     fn new(shape: EmitterShape) -> Self {
         Self {
-            default_params: ParticleParams {
-                size: 1.0,
-                color: Color::WHITE,
-                lifetime: 5.0,
-                ..Default::default()
-            },
-            default_speed: 0.0,
+            duration: Duration::from_secs(5),
+            looping: true,
+            start_delay: Duration::ZERO,
+            autoplay: true,
+            rate_over_time: None,
+            rate_over_distance: None,
+            default_params: ParticleParams::default(),
+            start_color: ParticleValue::Constant(Vec4::ONE),
+            start_lifetime: ParticleValue::Constant(5.0),
+            start_size: ParticleValue::Constant(1.0),
+            start_speed: ParticleValue::Constant(0.0),
             bursts: Vec::new(),
             shape,
+            emission: ShapeEmission::default(),
             modifiers: Vec::new(),
+            seed: None,
         }
     }
 
@@ -68,78 +655,192 @@ impl ParticleEmitterBuilder {
         self
     }
 
-    pub fn with_default_speed(mut self, speed: f32) -> Self {
-        self.default_speed = speed;
+    pub fn add_modifier_fn(
+        self,
+        modifier: impl FnMut(&mut ParticleParams, &mut EmitterContext) + Send + Sync + 'static,
+    ) -> Self {
+        self.add_modifier(FnEmitterModifier(modifier))
+    }
+
+    /// Sets the length of one cycle of the emitter, which rate curves are sampled over.
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Seeds the emitter's random number generator, making its emission deterministic. The
+    /// generator is reseeded whenever the emitter restarts.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Sets whether the emitter starts over at the end of its duration, or stops. Emitters
+    /// loop by default.
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Delays the start of emission after the emitter is spawned or restarted.
+    pub fn with_start_delay(mut self, delay: Duration) -> Self {
+        self.start_delay = delay;
+        self
+    }
+
+    /// Sets whether the emitter starts playing as soon as it is spawned. Emitters that do
+    /// not autoplay start paused until [`ParticleEmitter::play`] is called.
+    pub fn with_autoplay(mut self, autoplay: bool) -> Self {
+        self.autoplay = autoplay;
+        self
+    }
+
+    /// Emits a constant number of particles per second, in addition to any bursts.
+    pub fn with_rate_over_time(mut self, per_second: f32) -> Self {
+        self.rate_over_time = Some(EmissionRate::constant(per_second));
+        self
+    }
+
+    /// Emits a number of particles per second that can vary randomly or over the emitter's
+    /// duration, in addition to any bursts.
+    pub fn with_rate_over_time_value(mut self, per_second: ParticleValue<f32>) -> Self {
+        self.rate_over_time = Some(EmissionRate::new(per_second));
+        self
+    }
+
+    /// Emits a constant number of particles per unit that the emitter travels, in addition
+    /// to any bursts.
+    pub fn with_rate_over_distance(mut self, per_unit: f32) -> Self {
+        self.rate_over_distance = Some(EmissionRate::constant(per_unit));
+        self
+    }
+
+    /// Emits a number of particles per unit that the emitter travels, which can vary randomly
+    /// or over the emitter's duration, in addition to any bursts.
+    pub fn with_rate_over_distance_value(mut self, per_unit: ParticleValue<f32>) -> Self {
+        self.rate_over_distance = Some(EmissionRate::new(per_unit));
+        self
+    }
+
+    pub fn with_direction(mut self, direction: EmissionDirection) -> Self {
+        self.emission.direction = direction;
+        self
+    }
+
+    /// Randomly spreads emitted directions up to `spread` radians away from the emission
+    /// direction.
+    pub fn with_spread(mut self, spread: f32) -> Self {
+        self.emission.spread = spread;
+        self
+    }
+
+    /// Aligns each particle's start rotation to its direction of travel, as seen by a camera
+    /// looking down the Z axis. See [`ShapeEmission::align_rotation`].
+    pub fn with_aligned_rotation(mut self, align_rotation: bool) -> Self {
+        self.emission.align_rotation = align_rotation;
+        self
+    }
+
+    pub fn with_emission_mode(mut self, mode: EmissionMode) -> Self {
+        self.emission.mode = mode;
+        self
+    }
+
+    pub fn with_radius_thickness(mut self, radius_thickness: f32) -> Self {
+        self.emission.radius_thickness = radius_thickness;
+        self
+    }
+
+    pub fn with_radius_distribution(mut self, radius_distribution: RadiusDistribution) -> Self {
+        self.emission.radius_distribution = radius_distribution;
+        self
+    }
+
+    pub fn with_default_speed(self, speed: f32) -> Self {
+        self.with_start_speed(ParticleValue::Constant(speed))
+    }
+
+    pub fn with_default_color(self, color: Color) -> Self {
+        self.with_start_color(ParticleValue::Constant(Vec4::from(color.as_rgba_f32())))
+    }
+
+    pub fn with_default_lifetime(self, lifetime: f32) -> Self {
+        self.with_start_lifetime(ParticleValue::Constant(lifetime))
+    }
+
+    pub fn with_default_size(self, size: f32) -> Self {
+        self.with_start_size(ParticleValue::Constant(size))
+    }
+
+    /// Sets the start speed, which can vary randomly or over the emitter's duration.
+    pub fn with_start_speed(mut self, speed: ParticleValue<f32>) -> Self {
+        self.start_speed = speed;
         self
     }
 
-    pub fn with_default_color(mut self, color: Color) -> Self {
-        self.default_params.color = color;
+    /// Sets the start color as RGBA, which can vary randomly or follow a gradient over the
+    /// emitter's duration.
+    pub fn with_start_color(mut self, color: ParticleValue<Vec4>) -> Self {
+        self.start_color = color;
         self
     }
 
-    pub fn with_default_lifetime(mut self, lifetime: f32) -> Self {
-        self.default_params.lifetime = lifetime;
+    /// Sets the start lifetime in seconds, which can vary randomly or over the emitter's
+    /// duration.
+    pub fn with_start_lifetime(mut self, lifetime: ParticleValue<f32>) -> Self {
+        self.start_lifetime = lifetime;
         self
     }
 
-    pub fn with_default_size(mut self, size: f32) -> Self {
-        self.default_params.size = size;
+    /// Sets the start size, which can vary randomly or over the emitter's duration.
+    pub fn with_start_size(mut self, size: ParticleValue<f32>) -> Self {
+        self.start_size = size;
         self
     }
 
     pub fn build(self) -> ParticleEmitter {
         ParticleEmitter {
+            state: if self.autoplay {
+                EmitterState::Playing
+            } else {
+                EmitterState::Paused
+            },
+            clear_requested: false,
             next_burst: Duration::from_millis(0),
             burst_idx: 0,
+            burst_cycle: 0,
+            duration: self.duration,
+            looping: self.looping,
+            start_delay: self.start_delay,
+            delay_remaining: self.start_delay,
+            elapsed: Duration::ZERO,
+            age: Duration::ZERO,
+            seed: self.seed,
+            rng: match self.seed {
+                Some(seed) => SmallRng::seed_from_u64(seed),
+                None => SmallRng::from_entropy(),
+            },
+            rate_over_time: self.rate_over_time,
+            rate_over_distance: self.rate_over_distance,
+            last_position: None,
             default_params: self.default_params,
-            default_speed: self.default_speed,
+            start_color: self.start_color,
+            start_lifetime: self.start_lifetime,
+            start_size: self.start_size,
+            start_speed: self.start_speed,
             bursts: self.bursts,
             shape: self.shape,
+            emission: self.emission,
             modifiers: self.modifiers,
+            pending: Vec::new(),
         }
     }
 }
 
-pub enum EmitterShape {
-    Sphere { center: Vec3, radius: f32 },
-    Hemisphere { center: Vec3, radius: f32 },
-}
-
-impl EmitterShape {
-    pub fn sample(&self, rng: &mut impl Rng, params: &mut ParticleParams) {
-        match self {
-            Self::Sphere { radius, center } => Self::sample_sphere(*center, *radius, rng, params),
-            Self::Hemisphere { radius, center } => {
-                Self::sample_hemisphere(*center, *radius, rng, params)
-            }
-        }
-    }
-
-    fn sample_sphere(center: Vec3, radius: f32, rng: &mut impl Rng, params: &mut ParticleParams) {
-        let position = sample_sphere(rng);
-        let r = rng.gen_range(0.0..1.0);
-        params.position = position * r * radius + center;
-        params.velocity = position;
-    }
-
-    fn sample_hemisphere(
-        center: Vec3,
-        radius: f32,
-        rng: &mut impl Rng,
-        params: &mut ParticleParams,
-    ) {
-        let mut position = sample_sphere(rng);
-        position.y = f32::abs(position.y);
-        let r = rng.gen_range(0.0..1.0);
-        params.position = position * r * radius + center;
-        params.velocity = position;
-    }
-}
-
 pub fn emit_particles(
     time: Res<Time>,
     compute_task_pool: Res<ComputeTaskPool>,
+    shape_cache: Res<EmitterShapeCache>,
     mut particles: Query<(&mut ParticleEmitter, &mut Particles, &GlobalTransform)>,
 ) {
     let delta_time = time.delta();
@@ -147,80 +848,614 @@ pub fn emit_particles(
         &compute_task_pool,
         8,
         |(mut emitter, mut particles, transform)| {
-            let mut remaining = delta_time;
-            let mut rng = rand::thread_rng();
-            let mut total = 0;
-            while remaining > emitter.next_burst {
-                let EmitterBurst { count, wait } = emitter.bursts[emitter.burst_idx].clone();
-                let exact_count = rng.gen_range(count);
-                total += exact_count;
-
-                remaining -= emitter.next_burst;
-
-                emitter.next_burst = wait;
-                emitter.burst_idx = (emitter.burst_idx + 1) % emitter.bursts.len();
-            }
-
-            emitter.next_burst -= remaining;
-
-            if total > 0 {
-                let local_to_world = transform.compute_matrix();
-                let target_capacity = particles.len() + total;
-                particles.reserve(target_capacity);
-                for _ in 0..total {
-                    let mut params = emitter.default_params.clone();
-                    emitter.shape.sample(&mut rng, &mut params);
-                    params.velocity *= emitter.default_speed;
-                    params.position = local_to_world.transform_point3(params.position);
-                    params.velocity = local_to_world.transform_vector3(params.velocity);
-                    for modifier in emitter.modifiers.iter_mut() {
-                        modifier.modify(&mut params);
-                    }
-                    particles.spawn(params);
-                }
+            if emitter.clear_requested {
+                particles.clear();
+                emitter.clear_requested = false;
+            }
+            if !emitter.shape.is_ready(&shape_cache) {
+                emitter.last_position = None;
+                return;
             }
+
+            // The generator is copied out so that it can be borrowed alongside the emitter.
+            let mut rng = emitter.rng.clone();
+            if !emitter.pending.is_empty() {
+                emitter.emit_pending(&mut particles, transform, &shape_cache, &mut rng);
+            }
+            if emitter.state == EmitterState::Playing {
+                emitter.emit(
+                    &mut particles,
+                    transform,
+                    delta_time,
+                    &shape_cache,
+                    &mut rng,
+                );
+            } else {
+                emitter.last_position = None;
+            }
+            emitter.rng = rng;
         },
     );
 }
 
+/// The billboard rotation, in radians, that points a particle's X axis along `direction`
+/// as seen looking down the Z axis.
+///
+/// The view isn't known when particles are emitted, so the rotation is only correct for
+/// cameras looking down Z. Billboards aligned at draw time handle arbitrary views.
+fn align_rotation(direction: Vec3) -> f32 {
+    direction.y.atan2(direction.x)
+}
+
+/// Overrides for the parameters of particles emitted by an [`EmitParticles`] request.
+#[derive(Debug, Clone, Default)]
+pub struct ParticleOverrides {
+    pub color: Option<Color>,
+    pub size: Option<f32>,
+    pub lifetime: Option<f32>,
+    pub speed: Option<f32>,
+}
+
+/// A request to emit particles once from a particle system, outside of its emitter's
+/// schedule.
+///
+/// The target entity must have a [`ParticleEmitter`], whose shape and modifiers are reused.
+/// Particles are emitted at `position` with the emitter's rotation and scale, even if the
+/// emitter is paused or stopped. Requests can be sent as events or queued with
+/// [`EmitParticlesCommandsExt::emit_particles`].
+#[derive(Debug, Clone)]
+pub struct EmitParticles {
+    pub entity: Entity,
+    /// The world-space position to emit from.
+    pub position: Vec3,
+    /// If set, particles travel along this world-space direction, spread within
+    /// `cone_angle`, instead of the direction given by the emitter's shape. A zero direction
+    /// is ignored.
+    pub direction: Option<Vec3>,
+    /// The angle between `direction` and the edge of the cone particles are emitted in,
+    /// in radians.
+    pub cone_angle: f32,
+    pub count: Range<usize>,
+    pub overrides: ParticleOverrides,
+}
+
+impl EmitParticles {
+    pub fn new(entity: Entity, position: Vec3, count: Range<usize>) -> Self {
+        Self {
+            entity,
+            position,
+            direction: None,
+            cone_angle: 0.0,
+            count,
+            overrides: ParticleOverrides::default(),
+        }
+    }
+
+    pub fn with_direction(mut self, direction: Vec3, cone_angle: f32) -> Self {
+        self.direction = Some(direction);
+        self.cone_angle = cone_angle;
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.overrides.color = Some(color);
+        self
+    }
+
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.overrides.size = Some(size);
+        self
+    }
+
+    pub fn with_lifetime(mut self, lifetime: f32) -> Self {
+        self.overrides.lifetime = Some(lifetime);
+        self
+    }
+
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.overrides.speed = Some(speed);
+        self
+    }
+}
+
+impl Command for EmitParticles {
+    fn write(self, world: &mut World) {
+        match world.get_mut::<ParticleEmitter>(self.entity) {
+            Some(mut emitter) => emitter.pending.push(self),
+            None => warn!(
+                "Cannot emit particles from {:?}, which has no ParticleEmitter",
+                self.entity
+            ),
+        }
+    }
+}
+
+pub trait EmitParticlesCommandsExt {
+    /// Queues a one-shot emission, processed the next time particles are emitted.
+    fn emit_particles(&mut self, request: EmitParticles);
+}
+
+impl<'w, 's> EmitParticlesCommandsExt for Commands<'w, 's> {
+    fn emit_particles(&mut self, request: EmitParticles) {
+        self.add(request);
+    }
+}
+
+pub fn queue_emit_particles(
+    mut events: EventReader<EmitParticles>,
+    mut emitters: Query<&mut ParticleEmitter>,
+) {
+    for request in events.iter() {
+        match emitters.get_mut(request.entity) {
+            Ok(mut emitter) => emitter.pending.push(request.clone()),
+            Err(_) => warn!(
+                "Cannot emit particles from {:?}, which has no ParticleEmitter",
+                request.entity
+            ),
+        }
+    }
+}
+
+/// What happens to a particle system once its emitter has finished and all of its
+/// particles have died.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FinishAction {
+    /// Despawns the entity and its children.
+    Despawn,
+    /// Hides the entity, keeping it around to be restarted later. The emitter stays stopped,
+    /// and the entity is shown again once the emitter is played or restarted.
+    Disable,
+    /// Only sends a [`ParticleSystemFinished`] event.
+    Notify,
+}
+
+/// Acts on a particle system when its [`ParticleEmitter`] is finished and it has no live
+/// particles left. A [`ParticleSystemFinished`] event is sent regardless of the action.
+#[derive(Component, Debug, Clone)]
+pub struct ParticleSystemFinish {
+    pub action: FinishAction,
+    notified: bool,
+}
+
+impl ParticleSystemFinish {
+    pub fn new(action: FinishAction) -> Self {
+        Self {
+            action,
+            notified: false,
+        }
+    }
+
+    pub fn despawn() -> Self {
+        Self::new(FinishAction::Despawn)
+    }
+
+    pub fn disable() -> Self {
+        Self::new(FinishAction::Disable)
+    }
+
+    pub fn notify() -> Self {
+        Self::new(FinishAction::Notify)
+    }
+}
+
+/// Sent once when a particle system with a [`ParticleSystemFinish`] finishes. Sent again
+/// if the emitter is restarted and finishes again.
+#[derive(Debug, Clone)]
+pub struct ParticleSystemFinished {
+    pub entity: Entity,
+    pub action: FinishAction,
+}
+
+pub fn finish_particle_systems(
+    mut commands: Commands,
+    mut systems: Query<(
+        Entity,
+        &ParticleEmitter,
+        &Particles,
+        &mut ParticleSystemFinish,
+        Option<&mut Visibility>,
+    )>,
+    mut events: EventWriter<ParticleSystemFinished>,
+) {
+    for (entity, emitter, particles, mut finish, visibility) in systems.iter_mut() {
+        if !emitter.is_finished() || particles.len() > 0 {
+            if finish.notified {
+                finish.notified = false;
+                if let (FinishAction::Disable, Some(mut visibility)) = (finish.action, visibility) {
+                    visibility.is_visible = true;
+                }
+            }
+            continue;
+        }
+        if finish.notified {
+            continue;
+        }
+        finish.notified = true;
+        match finish.action {
+            FinishAction::Despawn => commands.entity(entity).despawn_recursive(),
+            FinishAction::Disable => {
+                if let Some(mut visibility) = visibility {
+                    visibility.is_visible = false;
+                }
+            }
+            FinishAction::Notify => {}
+        }
+        events.send(ParticleSystemFinished {
+            entity,
+            action: finish.action,
+        });
+    }
+}
+
+/// How often a [`TrailEmitter`] spawns trail particles behind each tracked particle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrailRate {
+    /// A number of trail particles per second.
+    PerSecond(f32),
+    /// A number of trail particles per unit travelled by the tracked particle.
+    PerDistance(f32),
+}
+
+/// Spawns particles into this entity's [`Particles`] behind every particle of another
+/// particle system.
+///
+/// Trail particles start at the tracked particle's position, size and color, have no
+/// velocity, and live for `lifetime` seconds. They are spread along the path each tracked
+/// particle moved since the last update. Fractional counts are rounded up or down at
+/// random, so low rates still produce trails on average.
 #[derive(Component, Clone, Debug)]
 pub struct TrailEmitter {
+    /// The entity whose particles are tracked.
     pub tracking: Entity,
     pub lifetime: f32,
+    pub rate: TrailRate,
+    rng: SmallRng,
+    pending: Vec<ParticleParams>,
+}
+
+impl TrailEmitter {
+    pub fn new(tracking: Entity, lifetime: f32, rate: TrailRate) -> Self {
+        Self {
+            tracking,
+            lifetime,
+            rate,
+            rng: SmallRng::from_entropy(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Seeds the trail's random number generator, making the rounding of fractional counts
+    /// deterministic.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = SmallRng::seed_from_u64(seed);
+        self
+    }
 }
 
 pub fn trail_particles(
+    time: Res<Time>,
+    compute_task_pool: Res<ComputeTaskPool>,
     mut particles: Query<&mut Particles>,
-    trails: Query<(Entity, &TrailEmitter)>,
+    mut trails: Query<(Entity, &mut TrailEmitter)>,
 ) {
-    for (entity, emitter) in trails.iter() {
+    let delta_time = time.delta_seconds_f64() as f32;
+    // Trails are gathered in parallel before any are spawned, as the tracked and trail
+    // systems live in the same query and may even be the same entity.
+    let sources = &particles;
+    trails.par_for_each_mut(&compute_task_pool, 8, |(_, mut emitter)| {
+        let emitter = &mut *emitter;
+        emitter.pending.clear();
+        let source = match sources.get(emitter.tracking) {
+            Ok(source) => source,
+            Err(_) => return,
+        };
+        for idx in 0..source.len() {
+            let position = source.positions[idx];
+            let previous = source.previous_positions[idx];
+            let expected = match emitter.rate {
+                TrailRate::PerSecond(rate) => rate * delta_time,
+                TrailRate::PerDistance(rate) => rate * previous.distance(position.xyz()),
+            };
+            let mut count = expected.max(0.0).floor();
+            if emitter.rng.gen::<f32>() < expected - count {
+                count += 1.0;
+            }
+            let count = count as usize;
+            for step in 0..count {
+                // Spread trail particles along the path travelled since the last update.
+                let t = (step + 1) as f32 / count as f32;
+                emitter.pending.push(ParticleParams {
+                    position: previous.lerp(position.xyz(), t),
+                    rotation: position.w,
+                    size: source.sizes[idx],
+                    color: Color::from(source.colors[idx]),
+                    lifetime: emitter.lifetime,
+                    ..Default::default()
+                });
+            }
+        }
+    });
+
+    for (entity, mut emitter) in trails.iter_mut() {
+        if emitter.pending.is_empty() {
+            continue;
+        }
         if let Ok(mut destination) = particles.get_mut(entity) {
-            let current_size = destination.len();
-            // if let Ok(source) = particles.get_unchecked(emitter.tracking) {
-            //     destination.reserve(current_size + source.len());
-            //     for particle in source.iter() {
-            //         destination.spawn(ParticleParams {
-            //             position: particle.position.xyz(),
-            //             rotation: particle.position.w,
-            //             size: *particle.size,
-            //             velocity: Vec3::ZERO,
-            //             angular_velocity: 0.0,
-            //             color: Color::from(*particle.color),
-            //             lifetime: emitter.lifetime,
-            //         });
-            //     }
-            // }
-        }
-    }
-}
-
-/// Select one point at random on the unit sphere.
-fn sample_sphere(rng: &mut impl Rng) -> Vec3 {
-    const TWO_PI: f32 = std::f32::consts::PI * 2.0;
-    let theta = rng.gen_range(0.0..TWO_PI);
-    let z = rng.gen_range(-1.0..1.0);
-    let x = f32::sqrt(1.0 - z * z) * f32::cos(theta);
-    let y = f32::sqrt(1.0 - z * z) * f32::sin(theta);
-
-    Vec3::from((x, y, z))
+            destination.spawn_batch(emitter.pending.drain(..));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::event::Events;
+
+    /// Emits over frames of the given lengths, in milliseconds, skipping frames where the
+    /// emitter isn't playing like [`emit_particles`] does. Returns the number of particles.
+    fn run(
+        emitter: &mut ParticleEmitter,
+        particles: &mut Particles,
+        frames: impl IntoIterator<Item = u64>,
+    ) -> usize {
+        let shape_cache = EmitterShapeCache::default();
+        let transform = GlobalTransform::identity();
+        let mut rng = SmallRng::seed_from_u64(0);
+        for frame in frames {
+            if emitter.state() == EmitterState::Playing {
+                let delta_time = Duration::from_millis(frame);
+                emitter.emit(particles, &transform, delta_time, &shape_cache, &mut rng);
+            }
+        }
+        particles.len()
+    }
+
+    fn emitter() -> ParticleEmitterBuilder {
+        ParticleEmitter::sphere(Vec3::ZERO, 1.0).with_seed(0)
+    }
+
+    #[test]
+    fn instant_bursts_fire_once_per_loop() {
+        let mut emitter = emitter()
+            .add_burst(EmitterBurst::default())
+            .with_duration(Duration::from_secs(1))
+            .build();
+        let mut particles = Particles::new(0);
+        assert_eq!(run(&mut emitter, &mut particles, [50; 10]), 1);
+        assert_eq!(run(&mut emitter, &mut particles, [50; 20]), 2);
+    }
+
+    #[test]
+    fn burst_cycles_step_by_interval() {
+        let burst = EmitterBurst {
+            cycles: 3,
+            interval: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let build = || {
+            emitter()
+                .add_burst(burst.clone())
+                .with_duration(Duration::from_secs(10))
+                .build()
+        };
+
+        let mut emitter = build();
+        let mut particles = Particles::new(0);
+        assert_eq!(run(&mut emitter, &mut particles, [250]), 3);
+        // Without a wait, the burst repeats one interval after its last cycle.
+        assert_eq!(run(&mut emitter, &mut particles, [100]), 4);
+
+        let mut emitter = build();
+        let mut particles = Particles::new(0);
+        assert_eq!(run(&mut emitter, &mut particles, [10; 25]), 3);
+        assert_eq!(run(&mut emitter, &mut particles, [10; 10]), 4);
+    }
+
+    #[test]
+    fn stops_at_the_end_of_its_duration() {
+        let mut emitter = emitter()
+            .with_rate_over_time(10.0)
+            .with_duration(Duration::from_secs(1))
+            .with_looping(false)
+            .build();
+        let mut particles = Particles::new(0);
+        assert_eq!(run(&mut emitter, &mut particles, [100; 20]), 10);
+        assert_eq!(emitter.state(), EmitterState::Stopped);
+        assert!(emitter.is_finished());
+    }
+
+    #[test]
+    fn loops_over_its_duration() {
+        let mut emitter = emitter()
+            .with_rate_over_time(10.0)
+            .with_duration(Duration::from_secs(1))
+            .build();
+        let mut particles = Particles::new(0);
+        assert_eq!(run(&mut emitter, &mut particles, [100; 25]), 25);
+        assert_eq!(emitter.state(), EmitterState::Playing);
+        assert!((emitter.progress() - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn waits_for_start_delay() {
+        let mut emitter = emitter()
+            .with_rate_over_time(10.0)
+            .with_start_delay(Duration::from_millis(500))
+            .build();
+        let mut particles = Particles::new(0);
+        assert_eq!(run(&mut emitter, &mut particles, [100; 5]), 0);
+        assert_eq!(run(&mut emitter, &mut particles, [100; 5]), 5);
+
+        // Restarting waits for the delay again.
+        emitter.restart();
+        assert_eq!(run(&mut emitter, &mut particles, [100; 5]), 5);
+        assert_eq!(run(&mut emitter, &mut particles, [100]), 6);
+    }
+
+    #[test]
+    fn play_pause_and_stop() {
+        let mut emitter = emitter()
+            .with_rate_over_time(10.0)
+            .with_duration(Duration::from_secs(2))
+            .with_autoplay(false)
+            .build();
+        let mut particles = Particles::new(0);
+        assert_eq!(emitter.state(), EmitterState::Paused);
+        assert_eq!(run(&mut emitter, &mut particles, [100; 5]), 0);
+
+        emitter.play();
+        assert_eq!(run(&mut emitter, &mut particles, [100; 5]), 5);
+
+        // Pausing keeps the emitter's progress.
+        emitter.pause();
+        assert_eq!(emitter.state(), EmitterState::Paused);
+        assert_eq!(run(&mut emitter, &mut particles, [100; 5]), 5);
+        assert!((emitter.progress() - 0.25).abs() < 1e-5);
+        emitter.play();
+        assert_eq!(run(&mut emitter, &mut particles, [100]), 6);
+        assert!((emitter.progress() - 0.3).abs() < 1e-5);
+
+        // Playing a stopped emitter restarts it.
+        emitter.stop_and_clear();
+        assert!(emitter.is_finished());
+        assert_eq!(run(&mut emitter, &mut particles, [100; 5]), 6);
+        emitter.play();
+        assert_eq!(emitter.state(), EmitterState::Playing);
+        assert_eq!(emitter.progress(), 0.0);
+        assert!(!emitter.clear_requested);
+    }
+
+    #[test]
+    fn rate_over_time_is_independent_of_frame_length() {
+        let counts: Vec<usize> = [vec![2000], vec![10; 200], vec![7, 33, 1, 59, 100]]
+            .into_iter()
+            .map(|frames| {
+                let mut emitter = emitter()
+                    .with_rate_over_time(30.0)
+                    .with_duration(Duration::from_secs(10))
+                    .build();
+                let mut particles = Particles::new(0);
+                // Repeat the frame lengths until two seconds have passed.
+                let frames = frames.repeat(2000 / frames.iter().sum::<u64>() as usize);
+                run(&mut emitter, &mut particles, frames)
+            })
+            .collect();
+        for count in counts {
+            assert!((59..=60).contains(&count), "emitted {} particles", count);
+        }
+    }
+
+    #[test]
+    fn rate_over_distance_needs_movement() {
+        let mut emitter = emitter().with_rate_over_distance(10.0).build();
+        let mut particles = Particles::new(0);
+        let shape_cache = EmitterShapeCache::default();
+        let mut rng = SmallRng::seed_from_u64(0);
+        let delta_time = Duration::from_millis(100);
+
+        let transform = GlobalTransform::from_translation(Vec3::ONE);
+        for _ in 0..10 {
+            emitter.emit(
+                &mut particles,
+                &transform,
+                delta_time,
+                &shape_cache,
+                &mut rng,
+            );
+        }
+        assert_eq!(particles.len(), 0);
+
+        let transform = GlobalTransform::from_translation(Vec3::new(1.0, 1.0, 2.0));
+        emitter.emit(
+            &mut particles,
+            &transform,
+            delta_time,
+            &shape_cache,
+            &mut rng,
+        );
+        assert!((9..=10).contains(&particles.len()));
+        emitter.emit(
+            &mut particles,
+            &transform,
+            delta_time,
+            &shape_cache,
+            &mut rng,
+        );
+        assert!((9..=10).contains(&particles.len()));
+    }
+
+    /// Spawns a finished particle system with the given action.
+    fn spawn_finished(world: &mut World, action: FinishAction) -> Entity {
+        let mut emitter = emitter().build();
+        emitter.stop();
+        world
+            .spawn()
+            .insert(emitter)
+            .insert(Particles::new(0))
+            .insert(ParticleSystemFinish::new(action))
+            .insert(Visibility::default())
+            .id()
+    }
+
+    fn finish(world: &mut World) -> Vec<ParticleSystemFinished> {
+        SystemStage::single(finish_particle_systems).run(world);
+        let mut events = world
+            .get_resource_mut::<Events<ParticleSystemFinished>>()
+            .unwrap();
+        let finished = events.drain().collect();
+        finished
+    }
+
+    fn finish_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(Events::<ParticleSystemFinished>::default());
+        world
+    }
+
+    #[test]
+    fn finish_waits_for_particles_to_die() {
+        let mut world = finish_world();
+        let entity = spawn_finished(&mut world, FinishAction::Notify);
+        world
+            .get_mut::<Particles>(entity)
+            .unwrap()
+            .spawn(ParticleParams::default());
+        assert!(finish(&mut world).is_empty());
+
+        world.get_mut::<Particles>(entity).unwrap().clear();
+        let finished = finish(&mut world);
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].entity, entity);
+        assert_eq!(finished[0].action, FinishAction::Notify);
+        // The event is only sent once per finish.
+        assert!(finish(&mut world).is_empty());
+        assert!(world.get::<Visibility>(entity).unwrap().is_visible);
+
+        // Restarting allows the system to finish again.
+        world.get_mut::<ParticleEmitter>(entity).unwrap().restart();
+        assert!(finish(&mut world).is_empty());
+        world.get_mut::<ParticleEmitter>(entity).unwrap().stop();
+        assert_eq!(finish(&mut world).len(), 1);
+    }
+
+    #[test]
+    fn finish_despawns() {
+        let mut world = finish_world();
+        let entity = spawn_finished(&mut world, FinishAction::Despawn);
+        assert_eq!(finish(&mut world)[0].action, FinishAction::Despawn);
+        assert!(world.get_entity(entity).is_none());
+    }
+
+    #[test]
+    fn finish_disables_until_restarted() {
+        let mut world = finish_world();
+        let entity = spawn_finished(&mut world, FinishAction::Disable);
+        assert_eq!(finish(&mut world)[0].action, FinishAction::Disable);
+        assert!(!world.get::<Visibility>(entity).unwrap().is_visible);
+        assert!(world.get::<ParticleEmitter>(entity).unwrap().is_finished());
+
+        world.get_mut::<ParticleEmitter>(entity).unwrap().play();
+        assert!(finish(&mut world).is_empty());
+        assert!(world.get::<Visibility>(entity).unwrap().is_visible);
+    }
 }
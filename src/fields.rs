@@ -0,0 +1,394 @@
+use crate::particles::{ParticleLayers, Particles};
+use bevy::{math::*, prelude::*, tasks::ComputeTaskPool};
+
+/// How the strength of a force field decreases with distance from its origin.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ForceFalloff {
+    /// Full strength everywhere inside the field's radius.
+    None,
+    /// Strength decreases linearly, reaching zero at the field's radius.
+    Linear,
+    /// Strength decreases quadratically, reaching zero at the field's radius.
+    Quadratic,
+}
+
+impl ForceFalloff {
+    #[inline]
+    fn attenuate(&self, distance: f32, radius: f32) -> f32 {
+        let t = (1.0 - distance / radius).clamp(0.0, 1.0);
+        match self {
+            Self::None => 1.0,
+            Self::Linear => t,
+            Self::Quadratic => t * t,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ForceFieldShape {
+    /// Accelerates particles toward the field's origin. Negative strengths push particles
+    /// away instead.
+    Point {
+        strength: f32,
+        radius: f32,
+        falloff: ForceFalloff,
+    },
+    /// Spins particles around an axis through the field's origin.
+    ///
+    /// `axis` is in the field's local space. `pull` additionally accelerates particles
+    /// toward the axis.
+    Vortex {
+        axis: Vec3,
+        strength: f32,
+        pull: f32,
+        radius: f32,
+        falloff: ForceFalloff,
+    },
+    /// Accelerates particles in a fixed direction while inside a box.
+    ///
+    /// Both `direction` and `half_extents` are in the field's local space.
+    Wind {
+        direction: Vec3,
+        strength: f32,
+        half_extents: Vec3,
+    },
+}
+
+/// A standalone force field that affects all [`Particles`] within its bounds.
+///
+/// Fields are positioned by the entity's [`GlobalTransform`] and only affect particle
+/// systems that share a layer with them.
+#[derive(Component, Debug, Clone)]
+pub struct ForceField {
+    pub shape: ForceFieldShape,
+    pub layers: ParticleLayers,
+}
+
+impl ForceField {
+    pub fn attractor(strength: f32, radius: f32, falloff: ForceFalloff) -> Self {
+        Self::from(ForceFieldShape::Point {
+            strength,
+            radius,
+            falloff,
+        })
+    }
+
+    pub fn repulsor(strength: f32, radius: f32, falloff: ForceFalloff) -> Self {
+        Self::attractor(-strength, radius, falloff)
+    }
+
+    pub fn vortex(axis: Vec3, strength: f32, radius: f32, falloff: ForceFalloff) -> Self {
+        Self::from(ForceFieldShape::Vortex {
+            axis,
+            strength,
+            pull: 0.0,
+            radius,
+            falloff,
+        })
+    }
+
+    pub fn wind(direction: Vec3, strength: f32, half_extents: Vec3) -> Self {
+        Self::from(ForceFieldShape::Wind {
+            direction,
+            strength,
+            half_extents,
+        })
+    }
+
+    pub fn with_layers(mut self, layers: ParticleLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+}
+
+impl From<ForceFieldShape> for ForceField {
+    fn from(shape: ForceFieldShape) -> Self {
+        Self {
+            shape,
+            layers: ParticleLayers::all(),
+        }
+    }
+}
+
+/// A force field with all of its parameters resolved into world space.
+struct WorldForceField {
+    layers: ParticleLayers,
+    origin: Vec3,
+    world_to_local: Mat4,
+    shape: ForceFieldShape,
+}
+
+impl WorldForceField {
+    fn new(field: &ForceField, transform: &GlobalTransform) -> Self {
+        let shape = match field.shape {
+            ForceFieldShape::Point {
+                strength,
+                radius,
+                falloff,
+            } => ForceFieldShape::Point {
+                strength,
+                radius: radius * transform.scale.max_element(),
+                falloff,
+            },
+            ForceFieldShape::Vortex {
+                axis,
+                strength,
+                pull,
+                radius,
+                falloff,
+            } => ForceFieldShape::Vortex {
+                axis: (transform.rotation * axis).normalize_or_zero(),
+                strength,
+                pull,
+                radius: radius * transform.scale.max_element(),
+                falloff,
+            },
+            ForceFieldShape::Wind {
+                direction,
+                strength,
+                half_extents,
+            } => ForceFieldShape::Wind {
+                direction: (transform.rotation * direction).normalize_or_zero(),
+                strength,
+                half_extents,
+            },
+        };
+        Self {
+            layers: field.layers,
+            origin: transform.translation,
+            world_to_local: transform.compute_matrix().inverse(),
+            shape,
+        }
+    }
+
+    /// Computes the acceleration applied to a particle at a given world position.
+    fn acceleration(&self, position: Vec3) -> Vec3 {
+        match self.shape {
+            ForceFieldShape::Point {
+                strength,
+                radius,
+                falloff,
+            } => {
+                let offset = self.origin - position;
+                let distance = offset.length();
+                if distance >= radius || distance <= f32::EPSILON {
+                    return Vec3::ZERO;
+                }
+                offset / distance * strength * falloff.attenuate(distance, radius)
+            }
+            ForceFieldShape::Vortex {
+                axis,
+                strength,
+                pull,
+                radius,
+                falloff,
+            } => {
+                let offset = position - self.origin;
+                let radial = offset - axis * offset.dot(axis);
+                let distance = radial.length();
+                if distance >= radius || distance <= f32::EPSILON {
+                    return Vec3::ZERO;
+                }
+                let attenuation = falloff.attenuate(distance, radius);
+                let tangent = axis.cross(radial / distance);
+                (tangent * strength - radial / distance * pull) * attenuation
+            }
+            ForceFieldShape::Wind {
+                direction,
+                strength,
+                half_extents,
+            } => {
+                let local = self.world_to_local.transform_point3(position);
+                if local.abs().cmple(half_extents).all() {
+                    direction * strength
+                } else {
+                    Vec3::ZERO
+                }
+            }
+        }
+    }
+}
+
+/// Accelerates every particle by the sum of the fields that share a layer with the system.
+fn apply_fields(
+    particles: &mut Particles,
+    layers: &ParticleLayers,
+    fields: &[WorldForceField],
+    delta_time: f32,
+) {
+    let fields: Vec<&WorldForceField> = fields
+        .iter()
+        .filter(|field| field.layers.intersects(layers))
+        .collect();
+    if fields.is_empty() {
+        return;
+    }
+
+    let positions = particles.positions.iter();
+    let velocities = particles.velocities.iter_mut();
+    for (position, velocity) in positions.zip(velocities) {
+        let acceleration = fields.iter().fold(Vec3::ZERO, |acc, field| {
+            acc + field.acceleration(position.xyz())
+        });
+        *velocity += Vec4::from((acceleration * delta_time, 0.0));
+    }
+}
+
+pub fn apply_force_fields(
+    time: Res<Time>,
+    compute_task_pool: Res<ComputeTaskPool>,
+    fields: Query<(&ForceField, &GlobalTransform)>,
+    mut particles: Query<(&mut Particles, Option<&ParticleLayers>)>,
+) {
+    let fields: Vec<WorldForceField> = fields
+        .iter()
+        .map(|(field, transform)| WorldForceField::new(field, transform))
+        .collect();
+    if fields.is_empty() {
+        return;
+    }
+
+    let delta_time = time.delta_seconds_f64() as f32;
+    particles.par_for_each_mut(&compute_task_pool, 8, |(mut particles, layers)| {
+        let layers = layers.copied().unwrap_or_default();
+        apply_fields(&mut particles, &layers, &fields, delta_time);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::particles::ParticleParams;
+
+    const EPSILON: f32 = 1e-5;
+
+    fn world_field(field: ForceField, transform: GlobalTransform) -> WorldForceField {
+        WorldForceField::new(&field, &transform)
+    }
+
+    fn assert_near(actual: Vec3, expected: Vec3) {
+        assert!(
+            actual.abs_diff_eq(expected, EPSILON),
+            "expected {}, found {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn point_fields_attract_and_repel() {
+        let transform = GlobalTransform::from_translation(Vec3::X);
+        let attractor = world_field(
+            ForceField::attractor(4.0, 2.0, ForceFalloff::None),
+            transform,
+        );
+        assert_near(
+            attractor.acceleration(Vec3::new(1.0, 1.0, 0.0)),
+            -Vec3::Y * 4.0,
+        );
+        assert_near(attractor.acceleration(Vec3::ZERO), Vec3::X * 4.0);
+        // Outside of the radius, and at the origin where there is no direction.
+        assert_near(attractor.acceleration(Vec3::new(1.0, 3.0, 0.0)), Vec3::ZERO);
+        assert_near(attractor.acceleration(Vec3::X), Vec3::ZERO);
+
+        let repulsor = world_field(
+            ForceField::repulsor(4.0, 2.0, ForceFalloff::None),
+            transform,
+        );
+        assert_near(
+            repulsor.acceleration(Vec3::new(1.0, 1.0, 0.0)),
+            Vec3::Y * 4.0,
+        );
+    }
+
+    #[test]
+    fn falloff_reaches_zero_at_radius() {
+        let position = Vec3::Y * 1.5;
+        let acceleration = |falloff| {
+            world_field(
+                ForceField::attractor(4.0, 2.0, falloff),
+                GlobalTransform::identity(),
+            )
+            .acceleration(position)
+        };
+        assert_near(acceleration(ForceFalloff::None), -Vec3::Y * 4.0);
+        assert_near(acceleration(ForceFalloff::Linear), -Vec3::Y);
+        assert_near(acceleration(ForceFalloff::Quadratic), -Vec3::Y * 0.25);
+        assert_eq!(ForceFalloff::Linear.attenuate(2.0, 2.0), 0.0);
+        assert_eq!(ForceFalloff::Quadratic.attenuate(0.0, 2.0), 1.0);
+    }
+
+    #[test]
+    fn vortex_spins_around_axis() {
+        let vortex = world_field(
+            ForceField::vortex(Vec3::Y, 2.0, 4.0, ForceFalloff::Linear),
+            GlobalTransform::identity(),
+        );
+        // Counter-clockwise around +Y, ignoring the height along the axis.
+        assert_near(vortex.acceleration(Vec3::new(2.0, 5.0, 0.0)), -Vec3::Z);
+        assert_near(vortex.acceleration(Vec3::new(0.0, -5.0, 2.0)), Vec3::X);
+        assert_near(vortex.acceleration(Vec3::new(0.0, 1.0, 0.0)), Vec3::ZERO);
+        assert_near(vortex.acceleration(Vec3::new(4.0, 0.0, 0.0)), Vec3::ZERO);
+
+        // The axis is rotated with the field, and pull accelerates toward it.
+        let vortex = world_field(
+            ForceField::from(ForceFieldShape::Vortex {
+                axis: Vec3::Y,
+                strength: 2.0,
+                pull: 1.0,
+                radius: 4.0,
+                falloff: ForceFalloff::None,
+            }),
+            GlobalTransform::from_rotation(Quat::from_rotation_x(std::f32::consts::FRAC_PI_2)),
+        );
+        assert_near(
+            vortex.acceleration(Vec3::new(2.0, 0.0, 0.0)),
+            Vec3::new(-1.0, 2.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn wind_blows_inside_box() {
+        let transform = GlobalTransform {
+            translation: Vec3::X * 10.0,
+            rotation: Quat::from_rotation_y(std::f32::consts::FRAC_PI_2),
+            ..Default::default()
+        };
+        let wind = world_field(
+            ForceField::wind(Vec3::X * 2.0, 3.0, Vec3::new(1.0, 1.0, 5.0)),
+            transform,
+        );
+        // The box's long Z axis is turned to lie along X.
+        assert_near(wind.acceleration(Vec3::X * 14.0), -Vec3::Z * 3.0);
+        assert_near(wind.acceleration(Vec3::new(10.0, 0.0, 2.0)), Vec3::ZERO);
+    }
+
+    #[test]
+    fn fields_only_affect_shared_layers() {
+        let fields = [
+            world_field(
+                ForceField::wind(Vec3::X, 1.0, Vec3::ONE).with_layers(ParticleLayers::layer(1)),
+                GlobalTransform::identity(),
+            ),
+            world_field(
+                ForceField::wind(Vec3::Y, 1.0, Vec3::ONE).with_layers(ParticleLayers::layer(2)),
+                GlobalTransform::identity(),
+            ),
+        ];
+        let run = |layers: ParticleLayers| {
+            let mut particles = Particles::new(1);
+            particles.spawn(ParticleParams {
+                lifetime: 1.0,
+                ..Default::default()
+            });
+            apply_fields(&mut particles, &layers, &fields, 0.5);
+            particles.velocities[0].xyz()
+        };
+        assert_near(run(ParticleLayers::default()), Vec3::ZERO);
+        assert_near(run(ParticleLayers::layer(1)), Vec3::X * 0.5);
+        assert_near(
+            run(ParticleLayers::layer(1).with(2)),
+            Vec3::new(0.5, 0.5, 0.0),
+        );
+    }
+}
@@ -0,0 +1,851 @@
+use crate::{
+    curve,
+    modifiers::{
+        ColorByLifetime, CombineMode, ConstantForce, ModifierContext, ParticleModifier,
+        SizeOverLifetime,
+    },
+    particles::Particles,
+};
+use bevy::{
+    asset::{AssetLoader, Assets, Handle, LoadContext, LoadedAsset},
+    math::{
+        curves::{Curve, CurveFixed},
+        *,
+    },
+    prelude::*,
+    reflect::TypeUuid,
+    render::camera::Camera,
+    tasks::ComputeTaskPool,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The index of a node within an [`EffectGraph`]. Nodes may only reference nodes that come
+/// before them.
+pub type NodeId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ValueType {
+    Float,
+    Vec3,
+    Vec4,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EffectValue {
+    Float(f32),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+}
+
+impl EffectValue {
+    fn value_type(&self) -> ValueType {
+        match self {
+            Self::Float(_) => ValueType::Float,
+            Self::Vec3(_) => ValueType::Vec3,
+            Self::Vec4(_) => ValueType::Vec4,
+        }
+    }
+}
+
+/// A per-particle channel that can be read or written by an effect graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Attribute {
+    Position,
+    Velocity,
+    Color,
+    Size,
+    Rotation,
+    AngularVelocity,
+    Frame,
+    /// Seconds since the particle was spawned. Read-only.
+    Age,
+    /// The ratio of the particle's lifetime that has passed. Read-only.
+    LifetimeRatio,
+}
+
+impl Attribute {
+    fn value_type(&self) -> ValueType {
+        match self {
+            Self::Position | Self::Velocity => ValueType::Vec3,
+            Self::Color => ValueType::Vec4,
+            Self::Size
+            | Self::Rotation
+            | Self::AngularVelocity
+            | Self::Frame
+            | Self::Age
+            | Self::LifetimeRatio => ValueType::Float,
+        }
+    }
+
+    fn is_writable(&self) -> bool {
+        !matches!(self, Self::Age | Self::LifetimeRatio)
+    }
+}
+
+/// When a write node is applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EffectContext {
+    /// Once, on the first update after a particle is spawned.
+    Spawn,
+    /// Every update.
+    Update,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MathOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Min,
+    Max,
+}
+
+/// A single node of an [`EffectGraph`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum EffectNode {
+    Constant(EffectValue),
+    /// The total time the particle system has been simulated for.
+    Time,
+    /// The time since the last update.
+    DeltaTime,
+    Read(Attribute),
+    Write {
+        context: EffectContext,
+        attribute: Attribute,
+        input: NodeId,
+    },
+    /// Applies an operation to two values of the same type. A float may be combined with a
+    /// vector, in which case it is applied to every component.
+    Math {
+        op: MathOp,
+        a: NodeId,
+        b: NodeId,
+    },
+    /// Samples evenly spaced keyframes with a float input, usually from 0.0 to 1.0.
+    SampleCurve {
+        input: NodeId,
+        keyframes: Vec<f32>,
+    },
+    /// Samples evenly spaced color keyframes with a float input, usually from 0.0 to 1.0.
+    SampleGradient {
+        input: NodeId,
+        keyframes: Vec<[f32; 4]>,
+    },
+    /// Samples smooth 3D value noise in `-1.0..1.0` for each axis from a Vec3 input.
+    Noise {
+        input: NodeId,
+        frequency: f32,
+    },
+    /// Runs [`ConstantForce`] over the whole particle system.
+    ConstantForce {
+        acceleration: [f32; 3],
+    },
+    /// Runs [`ColorByLifetime`] over the whole particle system.
+    ColorByLifetime {
+        keyframes: Vec<[f32; 4]>,
+        #[serde(default)]
+        combine: CombineMode,
+    },
+    /// Runs [`SizeOverLifetime`] over the whole particle system. Each keyframe is a
+    /// `(min, max)` range.
+    SizeOverLifetime {
+        keyframes: Vec<(f32, f32)>,
+        #[serde(default)]
+        combine: CombineMode,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum EffectGraphError {
+    /// A node references a node that does not come before it.
+    InvalidInput { node: NodeId, input: NodeId },
+    /// A node references a node that does not produce a value.
+    NoValue { node: NodeId, input: NodeId },
+    /// A node's input has the wrong type.
+    TypeMismatch {
+        node: NodeId,
+        expected: ValueType,
+        found: ValueType,
+    },
+    /// A write node targets a read-only attribute.
+    ReadOnlyAttribute { node: NodeId, attribute: Attribute },
+    /// A curve node has no keyframes.
+    EmptyCurve { node: NodeId },
+}
+
+impl fmt::Display for EffectGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidInput { node, input } => write!(
+                f,
+                "node {} references node {}, which is not defined before it",
+                node, input
+            ),
+            Self::NoValue { node, input } => write!(
+                f,
+                "node {} uses node {} as an input, but it does not produce a value",
+                node, input
+            ),
+            Self::TypeMismatch {
+                node,
+                expected,
+                found,
+            } => write!(
+                f,
+                "node {} expected an input of type {:?}, found {:?}",
+                node, expected, found
+            ),
+            Self::ReadOnlyAttribute { node, attribute } => write!(
+                f,
+                "node {} writes to {:?}, which is read-only",
+                node, attribute
+            ),
+            Self::EmptyCurve { node } => write!(f, "node {} has no keyframes", node),
+        }
+    }
+}
+
+impl std::error::Error for EffectGraphError {}
+
+#[derive(Debug, Clone, Copy)]
+enum Value {
+    Float(f32),
+    Vec3(Vec3),
+    Vec4(Vec4),
+}
+
+impl Value {
+    #[inline]
+    fn as_float(&self) -> f32 {
+        match self {
+            Self::Float(value) => *value,
+            _ => unreachable!("effect graphs are type checked when compiled"),
+        }
+    }
+
+    #[inline]
+    fn as_vec3(&self) -> Vec3 {
+        match self {
+            Self::Vec3(value) => *value,
+            _ => unreachable!("effect graphs are type checked when compiled"),
+        }
+    }
+
+    #[inline]
+    fn as_vec4(&self) -> Vec4 {
+        match self {
+            Self::Vec4(value) => *value,
+            _ => unreachable!("effect graphs are type checked when compiled"),
+        }
+    }
+}
+
+impl From<EffectValue> for Value {
+    fn from(value: EffectValue) -> Self {
+        match value {
+            EffectValue::Float(value) => Self::Float(value),
+            EffectValue::Vec3(value) => Self::Vec3(Vec3::from(value)),
+            EffectValue::Vec4(value) => Self::Vec4(Vec4::from(value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Op {
+    /// Nodes that do not produce a value.
+    None,
+    Constant(Value),
+    Time,
+    DeltaTime,
+    Read(Attribute),
+    Math(MathOp, NodeId, NodeId),
+    Curve(NodeId, CurveFixed<f32>),
+    Gradient(NodeId, CurveFixed<Vec4>),
+    Noise(NodeId, f32),
+}
+
+#[derive(Debug, Clone)]
+struct Write {
+    context: EffectContext,
+    attribute: Attribute,
+    input: NodeId,
+}
+
+#[derive(Debug, Clone)]
+enum Builtin {
+    ConstantForce(ConstantForce),
+    ColorByLifetime(ColorByLifetime),
+    SizeOverLifetime(SizeOverLifetime),
+}
+
+/// A data-driven particle effect made from a list of nodes.
+///
+/// Graphs are validated and compiled when created. Every update, each particle evaluates
+/// the value nodes in order and applies the write nodes for the current context. Built-in
+/// modifier nodes then run over the whole particle system in the order they were declared.
+///
+/// Graphs can be loaded from `.effect` files containing a RON list of [`EffectNode`]s.
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "9a3f0b2e-52a4-4c6b-b0d5-6a1f2e8c7d41"]
+pub struct EffectGraph {
+    nodes: Vec<EffectNode>,
+    ops: Vec<Op>,
+    writes: Vec<Write>,
+    builtins: Vec<Builtin>,
+}
+
+impl EffectGraph {
+    pub fn new(nodes: Vec<EffectNode>) -> Result<Self, EffectGraphError> {
+        let mut types: Vec<Option<ValueType>> = Vec::with_capacity(nodes.len());
+        let mut ops = Vec::with_capacity(nodes.len());
+        let mut writes = Vec::new();
+        let mut builtins = Vec::new();
+
+        for (node, kind) in nodes.iter().enumerate() {
+            let input_type = |input: NodeId| -> Result<ValueType, EffectGraphError> {
+                match types.get(input) {
+                    Some(Some(value_type)) => Ok(*value_type),
+                    Some(None) => Err(EffectGraphError::NoValue { node, input }),
+                    None => Err(EffectGraphError::InvalidInput { node, input }),
+                }
+            };
+            let expect = |input: NodeId, expected: ValueType| -> Result<(), EffectGraphError> {
+                let found = input_type(input)?;
+                if found == expected {
+                    Ok(())
+                } else {
+                    Err(EffectGraphError::TypeMismatch {
+                        node,
+                        expected,
+                        found,
+                    })
+                }
+            };
+
+            let (op, value_type) = match kind {
+                EffectNode::Constant(value) => {
+                    (Op::Constant(Value::from(*value)), Some(value.value_type()))
+                }
+                EffectNode::Time => (Op::Time, Some(ValueType::Float)),
+                EffectNode::DeltaTime => (Op::DeltaTime, Some(ValueType::Float)),
+                EffectNode::Read(attribute) => (Op::Read(*attribute), Some(attribute.value_type())),
+                EffectNode::Write {
+                    context,
+                    attribute,
+                    input,
+                } => {
+                    if !attribute.is_writable() {
+                        return Err(EffectGraphError::ReadOnlyAttribute {
+                            node,
+                            attribute: *attribute,
+                        });
+                    }
+                    expect(*input, attribute.value_type())?;
+                    writes.push(Write {
+                        context: *context,
+                        attribute: *attribute,
+                        input: *input,
+                    });
+                    (Op::None, None)
+                }
+                EffectNode::Math { op, a, b } => {
+                    let a_type = input_type(*a)?;
+                    let b_type = input_type(*b)?;
+                    let value_type = match (a_type, b_type) {
+                        (a_type, b_type) if a_type == b_type => a_type,
+                        (ValueType::Float, other) | (other, ValueType::Float) => other,
+                        (expected, found) => {
+                            return Err(EffectGraphError::TypeMismatch {
+                                node,
+                                expected,
+                                found,
+                            })
+                        }
+                    };
+                    (Op::Math(*op, *a, *b), Some(value_type))
+                }
+                EffectNode::SampleCurve { input, keyframes } => {
+                    expect(*input, ValueType::Float)?;
+                    if keyframes.is_empty() {
+                        return Err(EffectGraphError::EmptyCurve { node });
+                    }
+                    let curve = curve::from_vec(keyframes.clone());
+                    (Op::Curve(*input, curve), Some(ValueType::Float))
+                }
+                EffectNode::SampleGradient { input, keyframes } => {
+                    expect(*input, ValueType::Float)?;
+                    if keyframes.is_empty() {
+                        return Err(EffectGraphError::EmptyCurve { node });
+                    }
+                    let curve =
+                        curve::from_vec(keyframes.iter().copied().map(Vec4::from).collect());
+                    (Op::Gradient(*input, curve), Some(ValueType::Vec4))
+                }
+                EffectNode::Noise { input, frequency } => {
+                    expect(*input, ValueType::Vec3)?;
+                    (Op::Noise(*input, *frequency), Some(ValueType::Vec3))
+                }
+                EffectNode::ConstantForce { acceleration } => {
+                    builtins.push(Builtin::ConstantForce(ConstantForce {
+                        acceleration_per_second: Vec3::from(*acceleration),
+                    }));
+                    (Op::None, None)
+                }
+                EffectNode::ColorByLifetime { keyframes, combine } => {
+                    if keyframes.is_empty() {
+                        return Err(EffectGraphError::EmptyCurve { node });
+                    }
+                    let keyframes = keyframes.iter().copied().map(Vec4::from).collect();
+                    builtins.push(Builtin::ColorByLifetime(ColorByLifetime {
+                        color: curve::from_vec(keyframes),
+                        combine: *combine,
+                    }));
+                    (Op::None, None)
+                }
+                EffectNode::SizeOverLifetime { keyframes, combine } => {
+                    if keyframes.is_empty() {
+                        return Err(EffectGraphError::EmptyCurve { node });
+                    }
+                    let keyframes = keyframes.iter().map(|(min, max)| *min..*max).collect();
+                    builtins.push(Builtin::SizeOverLifetime(SizeOverLifetime {
+                        size: curve::from_vec(keyframes),
+                        combine: *combine,
+                    }));
+                    (Op::None, None)
+                }
+            };
+            ops.push(op);
+            types.push(value_type);
+        }
+
+        Ok(Self {
+            nodes,
+            ops,
+            writes,
+            builtins,
+        })
+    }
+
+    pub fn nodes(&self) -> &[EffectNode] {
+        &self.nodes
+    }
+
+    fn run(&self, particles: &mut Particles, ctx: &ModifierContext) {
+        if !self.writes.is_empty() {
+            let mut registers = vec![Value::Float(0.0); self.ops.len()];
+            for idx in 0..particles.len() {
+                let spawned = particles.starts[idx] >= particles.lifetime;
+                for (register, op) in self.ops.iter().enumerate() {
+                    registers[register] = match op {
+                        Op::None => continue,
+                        Op::Constant(value) => *value,
+                        Op::Time => Value::Float(ctx.time),
+                        Op::DeltaTime => Value::Float(ctx.delta_time),
+                        Op::Read(attribute) => read_attribute(particles, idx, *attribute),
+                        Op::Math(op, a, b) => math(*op, registers[*a], registers[*b]),
+                        Op::Curve(input, curve) => {
+                            Value::Float(curve.sample(registers[*input].as_float()))
+                        }
+                        Op::Gradient(input, curve) => {
+                            Value::Vec4(curve.sample(registers[*input].as_float()))
+                        }
+                        Op::Noise(input, frequency) => {
+                            Value::Vec3(noise3(registers[*input].as_vec3() * *frequency))
+                        }
+                    };
+                }
+                for write in self.writes.iter() {
+                    let spawn = write.context == EffectContext::Spawn;
+                    if !spawn || spawned {
+                        let value = registers[write.input];
+                        write_attribute(particles, idx, write.attribute, value, spawn);
+                    }
+                }
+            }
+        }
+
+        for builtin in self.builtins.iter() {
+            match builtin {
+                Builtin::ConstantForce(modifier) => modifier.apply(particles, ctx),
+                Builtin::ColorByLifetime(modifier) => modifier.apply(particles, ctx),
+                Builtin::SizeOverLifetime(modifier) => modifier.apply(particles, ctx),
+            }
+        }
+    }
+}
+
+fn read_attribute(particles: &Particles, idx: usize, attribute: Attribute) -> Value {
+    match attribute {
+        Attribute::Position => Value::Vec3(particles.positions[idx].xyz()),
+        Attribute::Velocity => Value::Vec3(particles.velocities[idx].xyz()),
+        Attribute::Color => Value::Vec4(particles.colors[idx]),
+        Attribute::Size => Value::Float(particles.sizes[idx]),
+        Attribute::Rotation => Value::Float(particles.positions[idx].w),
+        Attribute::AngularVelocity => Value::Float(particles.velocities[idx].w),
+        Attribute::Frame => Value::Float(particles.frames[idx]),
+        Attribute::Age => Value::Float(particles.lifetime - particles.starts[idx]),
+        Attribute::LifetimeRatio => {
+            let start = particles.starts[idx];
+            let end = particles.expirations[idx];
+            Value::Float((particles.lifetime - start) / (end - start))
+        }
+    }
+}
+
+/// Writes `value` to a particle. Spawn writes also replace the values colors, sizes and
+/// frames are reset to every frame.
+fn write_attribute(
+    particles: &mut Particles,
+    idx: usize,
+    attribute: Attribute,
+    value: Value,
+    spawn: bool,
+) {
+    match attribute {
+        Attribute::Position => {
+            let position = &mut particles.positions[idx];
+            *position = Vec4::from((value.as_vec3(), position.w));
+        }
+        Attribute::Velocity => {
+            let velocity = &mut particles.velocities[idx];
+            *velocity = Vec4::from((value.as_vec3(), velocity.w));
+        }
+        Attribute::Color => {
+            particles.colors[idx] = value.as_vec4();
+            if spawn {
+                particles.start_colors[idx] = value.as_vec4();
+            }
+        }
+        Attribute::Size => {
+            particles.sizes[idx] = value.as_float();
+            if spawn {
+                particles.start_sizes[idx] = value.as_float();
+            }
+        }
+        Attribute::Rotation => particles.positions[idx].w = value.as_float(),
+        Attribute::AngularVelocity => particles.velocities[idx].w = value.as_float(),
+        Attribute::Frame => {
+            particles.frames[idx] = value.as_float();
+            if spawn {
+                particles.start_frames[idx] = value.as_float();
+            }
+        }
+        Attribute::Age | Attribute::LifetimeRatio => {
+            unreachable!("effect graphs are validated when compiled")
+        }
+    }
+}
+
+fn math(op: MathOp, a: Value, b: Value) -> Value {
+    fn apply<T>(op: MathOp, a: T, b: T, min: fn(T, T) -> T, max: fn(T, T) -> T) -> T
+    where
+        T: std::ops::Add<Output = T>
+            + std::ops::Sub<Output = T>
+            + std::ops::Mul<Output = T>
+            + std::ops::Div<Output = T>,
+    {
+        match op {
+            MathOp::Add => a + b,
+            MathOp::Subtract => a - b,
+            MathOp::Multiply => a * b,
+            MathOp::Divide => a / b,
+            MathOp::Min => min(a, b),
+            MathOp::Max => max(a, b),
+        }
+    }
+
+    match (a, b) {
+        (Value::Float(a), Value::Float(b)) => Value::Float(apply(op, a, b, f32::min, f32::max)),
+        (Value::Vec3(a), Value::Vec3(b)) => Value::Vec3(apply(op, a, b, Vec3::min, Vec3::max)),
+        (Value::Vec3(a), Value::Float(b)) => {
+            Value::Vec3(apply(op, a, Vec3::splat(b), Vec3::min, Vec3::max))
+        }
+        (Value::Float(a), Value::Vec3(b)) => {
+            Value::Vec3(apply(op, Vec3::splat(a), b, Vec3::min, Vec3::max))
+        }
+        (Value::Vec4(a), Value::Vec4(b)) => Value::Vec4(apply(op, a, b, Vec4::min, Vec4::max)),
+        (Value::Vec4(a), Value::Float(b)) => {
+            Value::Vec4(apply(op, a, Vec4::splat(b), Vec4::min, Vec4::max))
+        }
+        (Value::Float(a), Value::Vec4(b)) => {
+            Value::Vec4(apply(op, Vec4::splat(a), b, Vec4::min, Vec4::max))
+        }
+        _ => unreachable!("effect graphs are type checked when compiled"),
+    }
+}
+
+/// Hashes an integer lattice point into `-1.0..1.0`.
+#[inline]
+fn hash(x: i32, y: i32, z: i32) -> f32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1_e995);
+    h ^= h >> 15;
+    (h as f32 / u32::MAX as f32) * 2.0 - 1.0
+}
+
+/// Smoothly interpolated value noise.
+fn value_noise(position: Vec3) -> f32 {
+    let cell = position.floor();
+    let t = position - cell;
+    let t = t * t * (Vec3::splat(3.0) - 2.0 * t);
+    let (x, y, z) = (cell.x as i32, cell.y as i32, cell.z as i32);
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let x00 = lerp(hash(x, y, z), hash(x + 1, y, z), t.x);
+    let x10 = lerp(hash(x, y + 1, z), hash(x + 1, y + 1, z), t.x);
+    let x01 = lerp(hash(x, y, z + 1), hash(x + 1, y, z + 1), t.x);
+    let x11 = lerp(hash(x, y + 1, z + 1), hash(x + 1, y + 1, z + 1), t.x);
+    lerp(lerp(x00, x10, t.y), lerp(x01, x11, t.y), t.z)
+}
+
+fn noise3(position: Vec3) -> Vec3 {
+    Vec3::new(
+        value_noise(position),
+        value_noise(position + Vec3::new(31.4, 0.0, 0.0)),
+        value_noise(position + Vec3::new(0.0, 0.0, 27.1)),
+    )
+}
+
+#[derive(Default)]
+pub struct EffectGraphLoader;
+
+impl AssetLoader for EffectGraphLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let nodes: Vec<EffectNode> = ron::de::from_bytes(bytes)?;
+            let graph = EffectGraph::new(nodes)?;
+            load_context.set_default_asset(LoadedAsset::new(graph));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["effect"]
+    }
+}
+
+pub fn apply_effect_graphs(
+    compute_task_pool: Res<ComputeTaskPool>,
+    time: Res<Time>,
+    graphs: Res<Assets<EffectGraph>>,
+    cameras: Query<&GlobalTransform, With<Camera>>,
+    mut particles: Query<(
+        Entity,
+        &Handle<EffectGraph>,
+        &mut Particles,
+        Option<&GlobalTransform>,
+    )>,
+) {
+    let delta_time = time.delta_seconds_f64() as f32;
+    let camera_position = cameras.iter().next().map(|transform| transform.translation);
+    particles.par_for_each_mut(
+        &compute_task_pool,
+        8,
+        |(entity, graph, mut particles, transform)| {
+            if let Some(graph) = graphs.get(graph) {
+                let ctx = ModifierContext::new(
+                    entity,
+                    delta_time,
+                    &particles,
+                    transform,
+                    camera_position,
+                );
+                graph.run(&mut particles, &ctx);
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParticleParams;
+
+    fn compile_error(nodes: Vec<EffectNode>) -> EffectGraphError {
+        EffectGraph::new(nodes).unwrap_err()
+    }
+
+    #[test]
+    fn rejects_mismatched_types() {
+        let error = compile_error(vec![
+            EffectNode::Constant(EffectValue::Float(1.0)),
+            EffectNode::Write {
+                context: EffectContext::Update,
+                attribute: Attribute::Velocity,
+                input: 0,
+            },
+        ]);
+        assert!(matches!(
+            error,
+            EffectGraphError::TypeMismatch {
+                node: 1,
+                expected: ValueType::Vec3,
+                found: ValueType::Float,
+            }
+        ));
+
+        let error = compile_error(vec![
+            EffectNode::Constant(EffectValue::Vec3([0.0; 3])),
+            EffectNode::Constant(EffectValue::Vec4([0.0; 4])),
+            EffectNode::Math {
+                op: MathOp::Add,
+                a: 0,
+                b: 1,
+            },
+        ]);
+        assert!(matches!(
+            error,
+            EffectGraphError::TypeMismatch { node: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn rejects_inputs_that_are_not_defined_before() {
+        let error = compile_error(vec![
+            EffectNode::Write {
+                context: EffectContext::Update,
+                attribute: Attribute::Size,
+                input: 1,
+            },
+            EffectNode::Constant(EffectValue::Float(1.0)),
+        ]);
+        assert!(matches!(
+            error,
+            EffectGraphError::InvalidInput { node: 0, input: 1 }
+        ));
+    }
+
+    #[test]
+    fn rejects_inputs_without_values() {
+        let error = compile_error(vec![
+            EffectNode::ConstantForce {
+                acceleration: [0.0, -9.8, 0.0],
+            },
+            EffectNode::Noise {
+                input: 0,
+                frequency: 1.0,
+            },
+        ]);
+        assert!(matches!(
+            error,
+            EffectGraphError::NoValue { node: 1, input: 0 }
+        ));
+    }
+
+    #[test]
+    fn rejects_writes_to_read_only_attributes() {
+        let error = compile_error(vec![
+            EffectNode::Constant(EffectValue::Float(1.0)),
+            EffectNode::Write {
+                context: EffectContext::Spawn,
+                attribute: Attribute::Age,
+                input: 0,
+            },
+        ]);
+        assert!(matches!(
+            error,
+            EffectGraphError::ReadOnlyAttribute {
+                node: 1,
+                attribute: Attribute::Age,
+            }
+        ));
+    }
+
+    #[test]
+    fn rejects_empty_curves() {
+        let error = compile_error(vec![
+            EffectNode::Read(Attribute::LifetimeRatio),
+            EffectNode::SampleCurve {
+                input: 0,
+                keyframes: Vec::new(),
+            },
+        ]);
+        assert!(matches!(error, EffectGraphError::EmptyCurve { node: 1 }));
+
+        let error = compile_error(vec![EffectNode::ColorByLifetime {
+            keyframes: Vec::new(),
+            combine: CombineMode::Replace,
+        }]);
+        assert!(matches!(error, EffectGraphError::EmptyCurve { node: 0 }));
+    }
+
+    #[test]
+    fn runs_spawn_and_update_writes() {
+        let graph = EffectGraph::new(vec![
+            EffectNode::Constant(EffectValue::Vec4([1.0, 0.0, 0.0, 1.0])),
+            EffectNode::Write {
+                context: EffectContext::Spawn,
+                attribute: Attribute::Color,
+                input: 0,
+            },
+            EffectNode::Read(Attribute::Velocity),
+            EffectNode::Constant(EffectValue::Float(2.0)),
+            EffectNode::Math {
+                op: MathOp::Multiply,
+                a: 2,
+                b: 3,
+            },
+            EffectNode::Write {
+                context: EffectContext::Update,
+                attribute: Attribute::Velocity,
+                input: 4,
+            },
+        ])
+        .unwrap();
+
+        let mut particles = Particles::new(2);
+        for _ in 0..2 {
+            particles.spawn(ParticleParams {
+                velocity: Vec3::X,
+                lifetime: 1.0,
+                ..Default::default()
+            });
+        }
+        let ctx = ModifierContext::new(Entity::from_raw(0), 0.1, &particles, None, None);
+        graph.run(&mut particles, &ctx);
+        let red = Vec4::new(1.0, 0.0, 0.0, 1.0);
+        assert_eq!(particles.colors[0], red);
+        assert_eq!(particles.start_colors[0], red);
+        assert_eq!(particles.velocities[0].xyz(), Vec3::X * 2.0);
+
+        // Spawn writes only apply on the first update after a particle is spawned.
+        particles.advance_particles(0.1);
+        particles.colors[0] = Vec4::ONE;
+        let ctx = ModifierContext::new(Entity::from_raw(0), 0.1, &particles, None, None);
+        graph.run(&mut particles, &ctx);
+        assert_eq!(particles.colors[0], Vec4::ONE);
+        assert_eq!(particles.velocities[0].xyz(), Vec3::X * 4.0);
+    }
+
+    #[test]
+    fn parses_effect_files() {
+        let source = r#"[
+            Read(LifetimeRatio),
+            SampleGradient(input: 0, keyframes: [(1.0, 1.0, 1.0, 1.0), (1.0, 1.0, 1.0, 0.0)]),
+            Write(context: Update, attribute: Color, input: 1),
+            ConstantForce(acceleration: (0.0, -9.8, 0.0)),
+        ]"#;
+        let nodes: Vec<EffectNode> = ron::de::from_str(source).unwrap();
+        let graph = EffectGraph::new(nodes).unwrap();
+        assert_eq!(graph.nodes().len(), 4);
+        assert!(matches!(
+            graph.nodes()[2],
+            EffectNode::Write {
+                context: EffectContext::Update,
+                attribute: Attribute::Color,
+                input: 1,
+            }
+        ));
+        assert_eq!(graph.writes.len(), 1);
+        assert_eq!(graph.builtins.len(), 1);
+    }
+}
@@ -0,0 +1,305 @@
+use bevy::{
+    math::*,
+    render::{render_resource::TextureFormat, texture::Image},
+};
+use std::fmt;
+
+/// The number of bisection steps used to refine a heightfield intersection.
+const REFINE_STEPS: usize = 8;
+
+#[derive(Debug, Clone)]
+pub enum HeightfieldError {
+    /// The grid must be at least 2x2 samples.
+    TooSmall { width: usize, depth: usize },
+    /// The number of heights does not match the grid dimensions.
+    SizeMismatch { expected: usize, found: usize },
+    /// The image's texture format cannot be read as heights.
+    UnsupportedFormat(TextureFormat),
+}
+
+impl fmt::Display for HeightfieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooSmall { width, depth } => write!(
+                f,
+                "heightfields must be at least 2x2 samples, found {}x{}",
+                width, depth
+            ),
+            Self::SizeMismatch { expected, found } => write!(
+                f,
+                "expected {} heightfield samples, found {}",
+                expected, found
+            ),
+            Self::UnsupportedFormat(format) => write!(
+                f,
+                "heightfields cannot be built from images with format {:?}",
+                format
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HeightfieldError {}
+
+/// A regular grid of heights, centered on the origin in the XZ plane.
+///
+/// Samples are stored row-major: `heights[z * width + x]`. The first sample lies at
+/// `-size / 2` and the last at `size / 2`.
+#[derive(Debug, Clone)]
+pub struct Heightfield {
+    width: usize,
+    depth: usize,
+    size: Vec2,
+    heights: Vec<f32>,
+}
+
+impl Heightfield {
+    pub fn from_grid(
+        width: usize,
+        depth: usize,
+        heights: Vec<f32>,
+        size: Vec2,
+    ) -> Result<Self, HeightfieldError> {
+        if width < 2 || depth < 2 {
+            return Err(HeightfieldError::TooSmall { width, depth });
+        }
+        if heights.len() != width * depth {
+            return Err(HeightfieldError::SizeMismatch {
+                expected: width * depth,
+                found: heights.len(),
+            });
+        }
+        Ok(Self {
+            width,
+            depth,
+            size,
+            heights,
+        })
+    }
+
+    /// Builds a heightfield from the first channel of an image, mapped into `0.0..=1.0`.
+    /// The heightfield spans one unit in each of X and Z.
+    pub fn from_image(image: &Image) -> Result<Self, HeightfieldError> {
+        let format = image.texture_descriptor.format;
+        let width = image.texture_descriptor.size.width as usize;
+        let depth = image.texture_descriptor.size.height as usize;
+        let heights = match format {
+            TextureFormat::R8Unorm => image.data.iter().map(|x| *x as f32 / 255.0).collect(),
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => image
+                .data
+                .chunks_exact(4)
+                .map(|texel| texel[0] as f32 / 255.0)
+                .collect(),
+            TextureFormat::R32Float => image
+                .data
+                .chunks_exact(4)
+                .map(|texel| f32::from_le_bytes([texel[0], texel[1], texel[2], texel[3]]))
+                .collect(),
+            _ => return Err(HeightfieldError::UnsupportedFormat(format)),
+        };
+        Self::from_grid(width, depth, heights, Vec2::ONE)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.size
+    }
+
+    /// Converts a local XZ position into continuous grid coordinates. Returns `None` if the
+    /// position lies outside of the heightfield.
+    #[inline]
+    fn to_grid(&self, x: f32, z: f32) -> Option<Vec2> {
+        let uv = (Vec2::new(x, z) / self.size) + Vec2::splat(0.5);
+        if uv.x < 0.0 || uv.y < 0.0 || uv.x > 1.0 || uv.y > 1.0 {
+            return None;
+        }
+        Some(uv * Vec2::new((self.width - 1) as f32, (self.depth - 1) as f32))
+    }
+
+    #[inline]
+    fn sample(&self, x: usize, z: usize) -> f32 {
+        self.heights[z * self.width + x]
+    }
+
+    /// Gets the bilinearly interpolated height at a local XZ position. Returns `None` if the
+    /// position lies outside of the heightfield.
+    pub fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+        let grid = self.to_grid(x, z)?;
+        let x0 = (grid.x.floor() as usize).min(self.width - 2);
+        let z0 = (grid.y.floor() as usize).min(self.depth - 2);
+        let tx = grid.x - x0 as f32;
+        let tz = grid.y - z0 as f32;
+        let top = self.sample(x0, z0) * (1.0 - tx) + self.sample(x0 + 1, z0) * tx;
+        let bottom = self.sample(x0, z0 + 1) * (1.0 - tx) + self.sample(x0 + 1, z0 + 1) * tx;
+        Some(top * (1.0 - tz) + bottom * tz)
+    }
+
+    /// Gets the surface normal at a local XZ position. Returns `None` if the position lies
+    /// outside of the heightfield.
+    pub fn normal_at(&self, x: f32, z: f32) -> Option<Vec3> {
+        let center = self.height_at(x, z)?;
+        let step = self.cell_size();
+        let dx = self.height_at(x + step.x, z).unwrap_or(center)
+            - self.height_at(x - step.x, z).unwrap_or(center);
+        let dz = self.height_at(x, z + step.y).unwrap_or(center)
+            - self.height_at(x, z - step.y).unwrap_or(center);
+        Some(Vec3::new(-dx / (2.0 * step.x), 1.0, -dz / (2.0 * step.y)).normalize())
+    }
+
+    #[inline]
+    fn cell_size(&self) -> Vec2 {
+        self.size / Vec2::new((self.width - 1) as f32, (self.depth - 1) as f32)
+    }
+
+    /// Signed vertical distance from the surface. `None` outside of the heightfield.
+    #[inline]
+    fn clearance(&self, position: Vec3) -> Option<f32> {
+        self.height_at(position.x, position.z)
+            .map(|height| position.y - height)
+    }
+
+    /// Sweeps a point along the segment from `start` to `end`, returning the fraction of the
+    /// segment travelled before it first passes below the surface, and the surface normal.
+    ///
+    /// Points that start below the surface, and any part of the segment outside of the
+    /// heightfield, do not collide.
+    pub fn sweep(&self, start: Vec3, end: Vec3) -> Option<(f32, Vec3)> {
+        if self
+            .clearance(start)
+            .map_or(false, |clearance| clearance < 0.0)
+        {
+            return None;
+        }
+
+        // March in steps no larger than a cell so that thin ridges are not skipped.
+        let delta = end - start;
+        let cell = self.cell_size().min_element();
+        let steps = ((Vec2::new(delta.x, delta.z).length() / cell).ceil() as usize).max(1);
+        let mut previous = 0.0;
+        for step in 1..=steps {
+            let time = step as f32 / steps as f32;
+            match self.clearance(start + delta * time) {
+                Some(clearance) if clearance < 0.0 => {
+                    let time = self.refine(start, delta, previous, time);
+                    let contact = start + delta * time;
+                    let normal = self.normal_at(contact.x, contact.z).unwrap_or(Vec3::Y);
+                    return Some((time, normal));
+                }
+                _ => previous = time,
+            }
+        }
+        None
+    }
+
+    /// Bisects the interval between a time above the surface and one below it.
+    fn refine(&self, start: Vec3, delta: Vec3, mut above: f32, mut below: f32) -> f32 {
+        for _ in 0..REFINE_STEPS {
+            let mid = (above + below) * 0.5;
+            match self.clearance(start + delta * mid) {
+                Some(clearance) if clearance < 0.0 => below = mid,
+                _ => above = mid,
+            }
+        }
+        above
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 3x3 grid spanning -1 to 1 where each sample's height is `x + 3 * z` in grid
+    /// coordinates, so bilinear interpolation is exact everywhere.
+    fn ramp() -> Heightfield {
+        let heights = (0..9).map(|idx| idx as f32).collect();
+        Heightfield::from_grid(3, 3, heights, Vec2::splat(2.0)).unwrap()
+    }
+
+    fn flat() -> Heightfield {
+        Heightfield::from_grid(2, 2, vec![0.0; 4], Vec2::splat(2.0)).unwrap()
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "expected {}, found {}", b, a);
+    }
+
+    #[test]
+    fn rejects_invalid_grids() {
+        assert!(Heightfield::from_grid(1, 3, vec![0.0; 3], Vec2::ONE).is_err());
+        assert!(Heightfield::from_grid(3, 3, vec![0.0; 8], Vec2::ONE).is_err());
+    }
+
+    #[test]
+    fn height_at_samples() {
+        let heightfield = ramp();
+        assert_close(heightfield.height_at(-1.0, -1.0).unwrap(), 0.0);
+        assert_close(heightfield.height_at(0.0, 0.0).unwrap(), 4.0);
+    }
+
+    #[test]
+    fn height_at_interpolates_within_cell() {
+        let heightfield = ramp();
+        assert_close(heightfield.height_at(-0.5, -0.5).unwrap(), 2.0);
+        assert_close(heightfield.height_at(0.25, 0.75).unwrap(), 6.5);
+    }
+
+    #[test]
+    fn height_at_max_edges() {
+        let heightfield = ramp();
+        assert_close(heightfield.height_at(1.0, 1.0).unwrap(), 8.0);
+        assert_close(heightfield.height_at(1.0, -1.0).unwrap(), 2.0);
+        assert_close(heightfield.height_at(-1.0, 1.0).unwrap(), 6.0);
+        assert_close(heightfield.height_at(1.0, 0.5).unwrap(), 6.5);
+    }
+
+    #[test]
+    fn height_at_out_of_bounds() {
+        let heightfield = ramp();
+        assert!(heightfield.height_at(1.01, 0.0).is_none());
+        assert!(heightfield.height_at(0.0, -1.5).is_none());
+        assert!(heightfield.height_at(-3.0, 3.0).is_none());
+    }
+
+    #[test]
+    fn sweep_hits_surface() {
+        let (time, normal) = flat()
+            .sweep(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0))
+            .unwrap();
+        assert!((time - 0.5).abs() < 0.01);
+        assert!(normal.abs_diff_eq(Vec3::Y, 1e-4));
+    }
+
+    #[test]
+    fn sweep_hits_while_moving_across_cells() {
+        let (time, _) = ramp()
+            .sweep(Vec3::new(-0.9, 5.0, 0.0), Vec3::new(0.9, 3.0, 0.0))
+            .unwrap();
+        let contact = Vec3::new(-0.9, 5.0, 0.0).lerp(Vec3::new(0.9, 3.0, 0.0), time);
+        let height = ramp().height_at(contact.x, contact.z).unwrap();
+        assert!((contact.y - height).abs() < 0.01);
+    }
+
+    #[test]
+    fn sweep_misses() {
+        let heightfield = flat();
+        // Stays above the surface.
+        assert!(heightfield
+            .sweep(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.5, 0.5, 0.0))
+            .is_none());
+        // Passes below the surface outside of the heightfield.
+        assert!(heightfield
+            .sweep(Vec3::new(5.0, 1.0, 5.0), Vec3::new(5.0, -1.0, 5.0))
+            .is_none());
+        // Starts below the surface.
+        assert!(heightfield
+            .sweep(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, -2.0, 0.0))
+            .is_none());
+    }
+}
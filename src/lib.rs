@@ -1,22 +1,49 @@
 use bevy::prelude::*;
 use bevy::render::primitives::Aabb;
+use bevy::transform::TransformSystem;
 
+mod beam;
+mod bvh;
+mod collision;
 pub mod curve;
 mod emitter;
+mod fields;
+mod graph;
+mod heightfield;
 mod material;
 pub mod modifiers;
 mod particles;
+mod patterns;
 mod render;
+mod ribbon;
+mod sampler;
+mod shape;
+mod volumes;
 
+pub use beam::*;
+pub use bvh::{MeshColliderError, TriangleBvh};
+pub use collision::*;
 pub use emitter::*;
+pub use fields::*;
+pub use graph::*;
+pub use heightfield::*;
 pub use material::*;
 use modifiers::*;
 pub use particles::*;
+pub use patterns::*;
 pub use render::*;
+pub use ribbon::*;
+pub use sampler::*;
+pub use shape::*;
+pub use volumes::*;
 
 use render::ParticleRenderPlugin;
 
+const PARTICLE_RESET: &str = "particle_reset";
 const PARTICLE_UPDATE: &str = "particle_update";
+const COLLIDER_CACHE: &str = "particle_collider_cache";
+const EMITTER_SHAPE_CACHE: &str = "particle_emitter_shape_cache";
+const PARTICLE_EMIT: &str = "particle_emit";
 
 pub struct ParticlePlugin;
 
@@ -24,12 +51,54 @@ impl Plugin for ParticlePlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(ParticleMaterialPlugin)
             .add_plugin(ParticleRenderPlugin)
+            .init_resource::<ParticleColliders>()
+            .init_resource::<MeshColliderCache>()
+            .init_resource::<HeightfieldCache>()
+            .init_resource::<EmitterShapeCache>()
+            .add_system(particles::reset_particles.label(PARTICLE_RESET))
             .add_system(particles::update_particles.label(PARTICLE_UPDATE))
-            .add_system(emitter::emit_particles.after(PARTICLE_UPDATE))
+            .add_system(collision::update_mesh_collider_cache.label(COLLIDER_CACHE))
+            .add_system(collision::update_heightfield_cache.label(COLLIDER_CACHE))
+            .add_system(
+                collision::collect_particle_colliders
+                    .after(COLLIDER_CACHE)
+                    .before(PARTICLE_UPDATE),
+            )
+            .add_system(fields::apply_force_fields.before(PARTICLE_UPDATE))
+            .add_asset::<EffectGraph>()
+            .init_asset_loader::<EffectGraphLoader>()
+            .add_system(
+                graph::apply_effect_graphs
+                    .after(PARTICLE_RESET)
+                    .before(PARTICLE_UPDATE),
+            )
+            .add_event::<ParticleVolumeEvent>()
+            .add_system(volumes::apply_particle_volumes.after(PARTICLE_UPDATE))
+            .add_system(shape::update_emitter_shape_cache.label(EMITTER_SHAPE_CACHE))
+            .add_event::<EmitParticles>()
+            .add_system(emitter::queue_emit_particles.before(PARTICLE_EMIT))
+            .add_system(
+                emitter::emit_particles
+                    .label(PARTICLE_EMIT)
+                    .after(PARTICLE_UPDATE)
+                    .after(EMITTER_SHAPE_CACHE),
+            )
             .add_system(emitter::trail_particles.after(PARTICLE_UPDATE))
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                beam::update_particle_beams.after(TransformSystem::TransformPropagate),
+            )
+            .add_event::<ParticleSystemFinished>()
+            .add_system(
+                emitter::finish_particle_systems
+                    .after(PARTICLE_UPDATE)
+                    .after(PARTICLE_EMIT),
+            )
             .register_particle_modifier::<ConstantForce>()
             .register_particle_modifier::<ColorByLifetime>()
-            .register_particle_modifier::<SizeOverLifetime>();
+            .register_particle_modifier::<SizeOverLifetime>()
+            .register_particle_modifier::<TextureSheetAnimation>()
+            .register_particle_modifier::<FnModifier>();
     }
 }
 
@@ -42,6 +111,7 @@ impl ParticleModifierAppExt for App {
         self.add_system(
             modifiers::apply_particle_modifier::<T>
                 .system()
+                .after(PARTICLE_RESET)
                 .before(PARTICLE_UPDATE),
         );
         self
@@ -17,6 +17,7 @@ bitflags::bitflags! {
     #[repr(transparent)]
     struct ParticleMaterialFlags: u32 {
         const BASE_COLOR_TEXTURE         = (1 << 0);
+        const FRAME_BLENDING             = (1 << 1);
         const NONE                       = 0;
         const UNINITIALIZED              = 0xFFFF;
     }
@@ -26,12 +27,35 @@ bitflags::bitflags! {
 #[uuid = "0078f73d-8715-427e-aa65-dc8e1f485d3d"]
 pub struct ParticleMaterial {
     pub base_color_texture: Option<Handle<Image>>,
+    /// The number of rows of frames in `base_color_texture` when used as a texture sheet.
+    pub rows: u32,
+    /// The number of columns of frames in `base_color_texture` when used as a texture sheet.
+    pub columns: u32,
+    /// If true, blends between the current and the next frame of the texture sheet based on
+    /// the fractional part of each particle's frame.
+    pub frame_blending: bool,
 }
 
 impl Default for ParticleMaterial {
     fn default() -> Self {
         Self {
             base_color_texture: None,
+            rows: 1,
+            columns: 1,
+            frame_blending: false,
+        }
+    }
+}
+
+impl ParticleMaterial {
+    /// Creates a material that uses `texture` as a texture sheet with the given number of
+    /// rows and columns of frames. Frames are numbered left to right, top to bottom.
+    pub fn texture_sheet(texture: Handle<Image>, rows: u32, columns: u32) -> Self {
+        Self {
+            base_color_texture: Some(texture),
+            rows,
+            columns,
+            ..Default::default()
         }
     }
 }
@@ -57,6 +81,8 @@ impl Plugin for ParticleMaterialPlugin {
 #[derive(Clone, AsStd140)]
 pub struct ParticleMaterialUniformData {
     pub flags: u32,
+    pub rows: u32,
+    pub columns: u32,
 }
 
 #[derive(Debug, Clone)]
@@ -82,7 +108,14 @@ impl RenderAsset for ParticleMaterial {
         if material.base_color_texture.is_some() {
             flags |= ParticleMaterialFlags::BASE_COLOR_TEXTURE;
         }
-        let value = ParticleMaterialUniformData { flags: flags.bits };
+        if material.frame_blending {
+            flags |= ParticleMaterialFlags::FRAME_BLENDING;
+        }
+        let value = ParticleMaterialUniformData {
+            flags: flags.bits,
+            rows: material.rows.max(1),
+            columns: material.columns.max(1),
+        };
         let value_std140 = value.as_std140();
 
         let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
@@ -1,18 +1,286 @@
 use crate::Particles;
 use bevy::{
     core::Time,
-    ecs::prelude::*,
+    ecs::{component::TableStorage, prelude::*},
     math::{
         curves::{Curve, CurveFixed},
         interpolation::Lerp,
         *,
     },
+    render::camera::Camera,
     tasks::ComputeTaskPool,
+    transform::components::GlobalTransform,
 };
-use std::ops::Range;
+use rand::{rngs::SmallRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::{marker::PhantomData, ops::Range};
+
+/// Per-frame information made available to every [`ParticleModifier`].
+#[derive(Debug, Clone)]
+pub struct ModifierContext {
+    /// The entity that owns the particle system being modified.
+    pub entity: Entity,
+    /// The time since the last update, in seconds.
+    pub delta_time: f32,
+    /// The total time the particle system has been simulated for, in seconds.
+    pub time: f32,
+    /// The global transform of the particle system. Identity if the entity has none.
+    pub transform: GlobalTransform,
+    /// The world-space position of the active camera, if there is one.
+    pub camera_position: Option<Vec3>,
+    seed: u64,
+}
+
+impl ModifierContext {
+    pub(crate) fn new(
+        entity: Entity,
+        delta_time: f32,
+        particles: &Particles,
+        transform: Option<&GlobalTransform>,
+        camera_position: Option<Vec3>,
+    ) -> Self {
+        Self {
+            entity,
+            delta_time,
+            time: particles.lifetime,
+            transform: transform.copied().unwrap_or_default(),
+            camera_position,
+            seed: entity.to_bits() ^ particles.lifetime.to_bits() as u64,
+        }
+    }
+
+    /// Creates a RNG that is deterministic for a given particle system and simulation time.
+    ///
+    /// Calling this multiple times in the same frame will yield identical sequences.
+    pub fn rng(&self) -> SmallRng {
+        SmallRng::seed_from_u64(self.seed)
+    }
+}
+
+bitflags::bitflags! {
+    /// The per-particle values a [`ParticleModifier`] writes to.
+    pub struct ParticleChannels: u32 {
+        const POSITIONS = 1 << 0;
+        const VELOCITIES = 1 << 1;
+        const COLORS = 1 << 2;
+        const SIZES = 1 << 3;
+        const FRAMES = 1 << 4;
+    }
+}
 
 pub trait ParticleModifier: Component {
-    fn apply(&self, particles: &mut Particles, delta_time: f32);
+    /// The values written by [`ParticleModifier::apply`]. Only these are blended when the
+    /// modifier is weighted by [`ModifierSettings`].
+    fn channels(&self) -> ParticleChannels {
+        ParticleChannels::all()
+    }
+
+    fn apply(&self, particles: &mut Particles, ctx: &ModifierContext);
+}
+
+/// A modifier backed by a closure, for one-off behaviors that don't warrant their own
+/// [`ParticleModifier`] type.
+#[derive(Component)]
+pub struct FnModifier {
+    channels: ParticleChannels,
+    func: Box<dyn Fn(&mut Particles, &ModifierContext) + Send + Sync>,
+}
+
+impl FnModifier {
+    /// Creates a modifier from a closure that writes to `channels`. Other channels are left
+    /// untouched when the modifier is weighted by [`ModifierSettings`].
+    pub fn new(
+        channels: ParticleChannels,
+        func: impl Fn(&mut Particles, &ModifierContext) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            channels,
+            func: Box::new(func),
+        }
+    }
+}
+
+impl ParticleModifier for FnModifier {
+    fn channels(&self) -> ParticleChannels {
+        self.channels
+    }
+
+    fn apply(&self, particles: &mut Particles, ctx: &ModifierContext) {
+        (self.func)(particles, ctx);
+    }
+}
+
+/// Controls how the [`ParticleModifier`] of type `T` on the same entity is applied.
+///
+/// The modifier's result is blended with each particle's unmodified state by `weight`, and
+/// only particles whose lifetime ratio lies within `lifetime_window` are affected. Each
+/// channel the modifier writes is blended with its value from before the modifier was
+/// applied, so the output of earlier modifiers is kept. Colors, sizes and frames are reset
+/// to their spawn values at the start of every update, so weighted modifiers that overwrite
+/// them do not accumulate over frames.
+pub struct ModifierSettings<T> {
+    pub enabled: bool,
+    pub weight: f32,
+    pub lifetime_window: Range<f32>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: ParticleModifier> Component for ModifierSettings<T> {
+    type Storage = TableStorage;
+}
+
+impl<T> Default for ModifierSettings<T> {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            weight: 1.0,
+            lifetime_window: 0.0..1.0,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for ModifierSettings<T> {
+    fn clone(&self) -> Self {
+        Self {
+            enabled: self.enabled,
+            weight: self.weight,
+            lifetime_window: self.lifetime_window.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for ModifierSettings<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ModifierSettings")
+            .field("enabled", &self.enabled)
+            .field("weight", &self.weight)
+            .field("lifetime_window", &self.lifetime_window)
+            .finish()
+    }
+}
+
+impl<T> ModifierSettings<T> {
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn with_lifetime_window(mut self, lifetime_window: Range<f32>) -> Self {
+        self.lifetime_window = lifetime_window;
+        self
+    }
+
+    /// Checks if the modifier would be applied to every particle at full strength.
+    fn is_passthrough(&self) -> bool {
+        self.enabled
+            && self.weight >= 1.0
+            && self.lifetime_window.start <= 0.0
+            && self.lifetime_window.end >= 1.0
+    }
+}
+
+/// Applies a modifier, then blends the result with each particle's unmodified state based
+/// on the provided settings.
+fn apply_weighted<T: ParticleModifier>(
+    modifier: &T,
+    settings: &ModifierSettings<T>,
+    particles: &mut Particles,
+    ctx: &ModifierContext,
+) {
+    let channels = modifier.channels();
+    let len = particles.len();
+    let positions = channels
+        .contains(ParticleChannels::POSITIONS)
+        .then(|| particles.positions.clone());
+    let velocities = channels
+        .contains(ParticleChannels::VELOCITIES)
+        .then(|| particles.velocities.clone());
+    let colors = channels
+        .contains(ParticleChannels::COLORS)
+        .then(|| particles.colors.clone());
+    let sizes = channels
+        .contains(ParticleChannels::SIZES)
+        .then(|| particles.sizes.clone());
+    let frames = channels
+        .contains(ParticleChannels::FRAMES)
+        .then(|| particles.frames.clone());
+
+    modifier.apply(particles, ctx);
+
+    // Modifiers that add or remove particles cannot be blended.
+    if particles.len() != len {
+        return;
+    }
+
+    for idx in 0..particles.len() {
+        // SAFE: idx is always a valid particle index.
+        let lifetime = unsafe { particles.lifetime_ratio(idx) };
+        let weight = if settings.lifetime_window.contains(&lifetime) {
+            settings.weight.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        if weight >= 1.0 {
+            continue;
+        }
+        if let Some(positions) = positions.as_ref() {
+            particles.positions[idx] = positions[idx].lerp(particles.positions[idx], weight);
+        }
+        if let Some(velocities) = velocities.as_ref() {
+            particles.velocities[idx] = velocities[idx].lerp(particles.velocities[idx], weight);
+        }
+        if let Some(colors) = colors.as_ref() {
+            particles.colors[idx] = colors[idx].lerp(particles.colors[idx], weight);
+        }
+        if let Some(sizes) = sizes.as_ref() {
+            particles.sizes[idx] = f32::lerp_unclamped(&sizes[idx], &particles.sizes[idx], weight);
+        }
+        if let Some(frames) = frames.as_ref() {
+            particles.frames[idx] =
+                f32::lerp_unclamped(&frames[idx], &particles.frames[idx], weight);
+        }
+    }
+}
+
+/// How a modifier's value is combined with the value a particle was spawned with.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CombineMode {
+    /// Discards the spawn value.
+    Replace,
+    /// Multiplies the spawn value by the modifier's value.
+    Multiply,
+    /// Adds the modifier's value to the spawn value.
+    Add,
+    /// Linearly interpolates from the spawn value to the modifier's value.
+    Lerp(f32),
+}
+
+impl Default for CombineMode {
+    fn default() -> Self {
+        Self::Replace
+    }
+}
+
+impl CombineMode {
+    #[inline]
+    pub fn combine<T>(&self, start: T, value: T) -> T
+    where
+        T: Lerp + std::ops::Add<Output = T> + std::ops::Mul<Output = T>,
+    {
+        match self {
+            Self::Replace => value,
+            Self::Multiply => start * value,
+            Self::Add => start + value,
+            Self::Lerp(t) => T::lerp_unclamped(&start, &value, *t),
+        }
+    }
 }
 
 #[derive(Component, Debug, Clone)]
@@ -24,15 +292,22 @@ pub struct ColorBySpeed {
 #[derive(Component, Debug, Clone)]
 pub struct ColorByLifetime {
     pub color: CurveFixed<Vec4>,
+    pub combine: CombineMode,
 }
 
 impl ParticleModifier for ColorByLifetime {
-    fn apply(&self, particles: &mut Particles, _: f32) {
+    fn channels(&self) -> ParticleChannels {
+        ParticleChannels::COLORS
+    }
+
+    fn apply(&self, particles: &mut Particles, _: &ModifierContext) {
         for idx in 0..particles.len() {
             // SAFE: idx is always a valid particle index.
             unsafe {
                 let lifetime = particles.lifetime_ratio(idx);
-                *particles.colors.get_unchecked_mut(idx) = self.color.sample(lifetime);
+                let start = *particles.start_colors.get_unchecked(idx);
+                *particles.colors.get_unchecked_mut(idx) =
+                    self.combine.combine(start, self.color.sample(lifetime));
             }
         }
     }
@@ -49,8 +324,12 @@ pub struct ConstantForce {
 }
 
 impl ParticleModifier for ConstantForce {
-    fn apply(&self, particles: &mut Particles, delta_time: f32) {
-        let delta_velocity = Vec4::from((self.acceleration_per_second, 0.0)) * delta_time;
+    fn channels(&self) -> ParticleChannels {
+        ParticleChannels::VELOCITIES
+    }
+
+    fn apply(&self, particles: &mut Particles, ctx: &ModifierContext) {
+        let delta_velocity = Vec4::from((self.acceleration_per_second, 0.0)) * ctx.delta_time;
         for velocity in particles.velocities.iter_mut() {
             *velocity += delta_velocity;
         }
@@ -89,18 +368,75 @@ pub struct SizeBySpeed {
 #[derive(Component, Debug, Clone)]
 pub struct SizeOverLifetime {
     pub size: CurveFixed<Range<f32>>,
+    pub combine: CombineMode,
 }
 
 impl ParticleModifier for SizeOverLifetime {
-    fn apply(&self, particles: &mut Particles, _delta_time: f32) {
+    fn channels(&self) -> ParticleChannels {
+        ParticleChannels::SIZES
+    }
+
+    fn apply(&self, particles: &mut Particles, _: &ModifierContext) {
         for idx in 0..particles.len() {
             // SAFE: idx is always a valid particle index.
             unsafe {
                 let lifetime = particles.lifetime_ratio(idx);
                 let range = self.size.sample(lifetime);
                 let lerp_factor = particles.lerp_factors.get_unchecked(idx);
-                *particles.sizes.get_unchecked_mut(idx) =
-                    f32::lerp_unclamped(&range.start, &range.end, *lerp_factor);
+                let start = *particles.start_sizes.get_unchecked(idx);
+                let size = f32::lerp_unclamped(&range.start, &range.end, *lerp_factor);
+                *particles.sizes.get_unchecked_mut(idx) = self.combine.combine(start, size);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SheetAnimationMode {
+    /// Samples the normalized frame, from 0.0 to 1.0, over the particle's lifetime. A value
+    /// of 1.0 is the last frame.
+    OverLifetime(CurveFixed<f32>),
+    /// Advances a constant number of frames per second, cycling back to the first frame.
+    Fps(f32),
+}
+
+/// Animates particles through the frames of their material's texture sheet.
+///
+/// `frames` is usually the material's `rows * columns`, but can be smaller to only use the
+/// first frames of a sheet.
+#[derive(Component, Debug, Clone)]
+pub struct TextureSheetAnimation {
+    pub frames: u32,
+    pub mode: SheetAnimationMode,
+    /// If true, each particle starts on a random frame.
+    pub random_start_frame: bool,
+}
+
+impl ParticleModifier for TextureSheetAnimation {
+    fn channels(&self) -> ParticleChannels {
+        ParticleChannels::FRAMES
+    }
+
+    fn apply(&self, particles: &mut Particles, _: &ModifierContext) {
+        let frames = self.frames.max(1) as f32;
+        for idx in 0..particles.len() {
+            // SAFE: idx is always a valid particle index.
+            unsafe {
+                let frame = match &self.mode {
+                    SheetAnimationMode::OverLifetime(curve) => {
+                        let frame = curve.sample(particles.lifetime_ratio(idx)) * frames;
+                        frame.min(frames - 1.0)
+                    }
+                    SheetAnimationMode::Fps(fps) => {
+                        (particles.lifetime - particles.starts.get_unchecked(idx)) * fps
+                    }
+                };
+                let offset = if self.random_start_frame {
+                    (particles.lerp_factors.get_unchecked(idx) * frames).floor()
+                } else {
+                    0.0
+                };
+                *particles.frames.get_unchecked_mut(idx) = (frame + offset).rem_euclid(frames);
             }
         }
     }
@@ -112,10 +448,148 @@ pub struct VelocityOverLifetime {}
 pub fn apply_particle_modifier<T: ParticleModifier>(
     compute_task_pool: Res<ComputeTaskPool>,
     time: Res<Time>,
-    mut particles: Query<(&T, &mut Particles)>,
+    cameras: Query<&GlobalTransform, With<Camera>>,
+    mut particles: Query<(
+        Entity,
+        &T,
+        &mut Particles,
+        Option<&GlobalTransform>,
+        Option<&ModifierSettings<T>>,
+    )>,
 ) {
     let delta_time = time.delta_seconds_f64() as f32;
-    particles.par_for_each_mut(&compute_task_pool, 8, |(modifier, mut particles)| {
-        modifier.apply(&mut particles, delta_time);
-    });
+    let camera_position = cameras.iter().next().map(|transform| transform.translation);
+    particles.par_for_each_mut(
+        &compute_task_pool,
+        8,
+        |(entity, modifier, mut particles, transform, settings)| {
+            let ctx =
+                ModifierContext::new(entity, delta_time, &particles, transform, camera_position);
+            match settings {
+                Some(settings) if !settings.enabled || settings.weight <= 0.0 => {}
+                Some(settings) if !settings.is_passthrough() => {
+                    apply_weighted(modifier, settings, &mut particles, &ctx);
+                }
+                _ => modifier.apply(&mut particles, &ctx),
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{curve, ParticleParams};
+    use bevy::render::color::Color;
+
+    #[test]
+    fn weighted_modifiers_do_not_accumulate() {
+        let mut particles = Particles::new(1);
+        particles.spawn(ParticleParams {
+            color: Color::BLACK,
+            size: 1.0,
+            lifetime: 1.0,
+            ..Default::default()
+        });
+        let ctx = ModifierContext::new(Entity::from_raw(0), 0.1, &particles, None, None);
+
+        let color = ColorByLifetime {
+            color: curve::from_vec(vec![Vec4::ONE]),
+            combine: CombineMode::Replace,
+        };
+        let size = SizeOverLifetime {
+            size: curve::from_constant_vec(vec![3.0]),
+            combine: CombineMode::Replace,
+        };
+        let color_settings = ModifierSettings::default().with_weight(0.5);
+        let size_settings = ModifierSettings::default().with_weight(0.5);
+        for _ in 0..4 {
+            particles.reset_derived();
+            apply_weighted(&color, &color_settings, &mut particles, &ctx);
+            apply_weighted(&size, &size_settings, &mut particles, &ctx);
+        }
+        assert_eq!(particles.colors[0], Vec4::new(0.5, 0.5, 0.5, 1.0));
+        assert_eq!(particles.sizes[0], 2.0);
+    }
+
+    #[test]
+    fn weighted_forces_scale_each_step() {
+        let mut particles = Particles::new(1);
+        particles.spawn(ParticleParams {
+            lifetime: 1.0,
+            ..Default::default()
+        });
+        let settings = ModifierSettings::default().with_weight(0.5);
+        let ctx = ModifierContext::new(Entity::from_raw(0), 1.0, &particles, None, None);
+
+        let force = ConstantForce {
+            acceleration_per_second: Vec3::X,
+        };
+        apply_weighted(&force, &settings, &mut particles, &ctx);
+        apply_weighted(&force, &settings, &mut particles, &ctx);
+        assert_eq!(particles.velocities[0], Vec4::new(1.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn weighted_modifiers_keep_earlier_output() {
+        let mut particles = Particles::new(1);
+        particles.spawn(ParticleParams {
+            color: Color::BLACK,
+            lifetime: 1.0,
+            ..Default::default()
+        });
+        let ctx = ModifierContext::new(Entity::from_raw(0), 1.0, &particles, None, None);
+
+        let color = ColorByLifetime {
+            color: curve::from_vec(vec![Vec4::ONE]),
+            combine: CombineMode::Replace,
+        };
+        let push = FnModifier::new(ParticleChannels::VELOCITIES, |particles, _| {
+            for velocity in particles.velocities.iter_mut() {
+                *velocity += Vec4::X;
+            }
+        });
+        let settings = ModifierSettings::default().with_weight(0.5);
+        color.apply(&mut particles, &ctx);
+        apply_weighted(&push, &settings, &mut particles, &ctx);
+        assert_eq!(particles.colors[0], Vec4::ONE);
+        assert_eq!(particles.velocities[0], Vec4::new(0.5, 0.0, 0.0, 0.0));
+
+        // Outside of the lifetime window the modifier has no effect on any channel.
+        let settings = ModifierSettings::default().with_lifetime_window(0.5..1.0);
+        let fade = FnModifier::new(ParticleChannels::COLORS, |particles, _| {
+            particles
+                .colors
+                .iter_mut()
+                .for_each(|color| *color = Vec4::ZERO);
+        });
+        apply_weighted(&fade, &settings, &mut particles, &ctx);
+        assert_eq!(particles.colors[0], Vec4::ONE);
+    }
+
+    #[test]
+    fn sheet_animation_ends_on_last_frame() {
+        let mut particles = Particles::new(2);
+        for _ in 0..2 {
+            particles.spawn(ParticleParams {
+                lifetime: 1.0,
+                ..Default::default()
+            });
+        }
+        particles.lifetime = 1.0;
+        let ctx = ModifierContext::new(Entity::from_raw(0), 0.1, &particles, None, None);
+
+        let mut animation = TextureSheetAnimation {
+            frames: 4,
+            mode: SheetAnimationMode::OverLifetime(curve::from_vec(vec![1.0])),
+            random_start_frame: false,
+        };
+        animation.apply(&mut particles, &ctx);
+        assert_eq!(particles.frames[0], 3.0);
+
+        // Animating by frame rate keeps cycling through the sheet.
+        animation.mode = SheetAnimationMode::Fps(5.0);
+        animation.apply(&mut particles, &ctx);
+        assert_eq!(particles.frames[0], 1.0);
+    }
 }
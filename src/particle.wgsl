@@ -1,7 +1,13 @@
 // TODO: try merging this block with the binding?
 struct View {
     view_proj: mat4x4<f32>;
+    inverse_view: mat4x4<f32>;
+    projection: mat4x4<f32>;
     world_position: vec3<f32>;
+    near: f32;
+    far: f32;
+    width: f32;
+    height: f32;
 };
 [[group(0), binding(0)]]
 var<uniform> view: View;
@@ -9,12 +15,23 @@ var<uniform> view: View;
 struct PositionBuffer { data: array<vec4<f32>>; };
 struct SizeBuffer { data: array<f32>; };
 struct ColorBuffer { data: array<vec4<f32>>; };
+struct FrameBuffer { data: array<f32>; };
+struct VelocityBuffer { data: array<vec4<f32>>; };
+
+struct Alignment {
+  axis: vec4<f32>;
+  first_particle: u32;
+  first_velocity: u32;
+};
 
 struct ParticleMaterial {
   flags: u32;
+  rows: u32;
+  columns: u32;
 };
 
 let FLAGS_BASE_COLOR_TEXTURE_BIT: u32         = 1u;
+let FLAGS_FRAME_BLENDING_BIT: u32             = 2u;
 
 [[group(1), binding(0)]]
 var<storage, read> positions: PositionBuffer;
@@ -22,6 +39,12 @@ var<storage, read> positions: PositionBuffer;
 var<storage, read> sizes: SizeBuffer;
 [[group(1), binding(2)]]
 var<storage, read> colors:ColorBuffer;
+[[group(1), binding(3)]]
+var<storage, read> frames: FrameBuffer;
+[[group(1), binding(4)]]
+var<storage, read> velocities: VelocityBuffer;
+[[group(1), binding(5)]]
+var<uniform> alignment: Alignment;
 [[group(2), binding(0)]]
 var<uniform> material: ParticleMaterial;
 [[group(2), binding(1)]]
@@ -37,8 +60,23 @@ struct VertexOutput {
   [[builtin(position)]] position: vec4<f32>;
   [[location(0)]] color: vec4<f32>;
   [[location(1)]] uv: vec2<f32>;
+  [[location(2)]] next_uv: vec2<f32>;
+  [[location(3)]] frame_blend: f32;
 };
 
+// Rotates a vector by a quaternion.
+fn quat_rotate(q: vec4<f32>, v: vec3<f32>) -> vec3<f32> {
+  return v + 2.0 * cross(q.xyz, cross(q.xyz, v) + q.w * v);
+}
+
+// Remaps a quad UV into the cell of a frame in the material's texture sheet.
+fn sheet_uv(uv: vec2<f32>, frame: u32) -> vec2<f32> {
+  let column = frame % material.columns;
+  let row = frame / material.columns;
+  return (vec2<f32>(f32(column), f32(row)) + uv) /
+    vec2<f32>(f32(material.columns), f32(material.rows));
+}
+
 [[stage(vertex)]]
 fn vs_main(model: VertexInput) -> VertexOutput {
   var vertex_positions: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
@@ -72,8 +110,50 @@ fn vs_main(model: VertexInput) -> VertexOutput {
   let particle_position = positions.data[particle_idx].xyz;
   let theta = positions.data[particle_idx].w;
   let size = sizes.data[particle_idx];
-  let sin_cos = vec2<f32>(cos(theta), sin(theta));
-  
+  let axis = alignment.axis;
+  let to_camera = normalize(view.world_position - particle_position);
+
+  // The quad's world-space X and Y axes, and its size along each of them.
+  var right: vec3<f32> = camera_right;
+  var up: vec3<f32> = camera_up;
+  var quad_size: vec2<f32> = vec2<f32>(size, size);
+  var sin_cos: vec2<f32> = vec2<f32>(cos(theta), sin(theta));
+
+#ifdef ALIGN_FACING_CAMERA
+  let facing_right = cross(vec3<f32>(0.0, 1.0, 0.0), to_camera);
+  if (length(facing_right) > 0.0001) {
+    right = normalize(facing_right);
+    up = cross(to_camera, right);
+  }
+#endif
+
+#ifdef ALIGN_VELOCITY
+  // Stretched billboards are never rotated, their length runs along the velocity.
+  let velocity_idx = particle_idx - alignment.first_particle + alignment.first_velocity;
+  let velocity = velocities.data[velocity_idx].xyz * axis.x;
+  let speed = length(velocity);
+  let velocity_right = cross(velocity, to_camera);
+  if (speed > 0.0 && length(velocity_right) > 0.0001) {
+    up = velocity / speed;
+    right = normalize(velocity_right);
+  }
+  quad_size = vec2<f32>(size, size * axis.y + speed);
+  sin_cos = vec2<f32>(1.0, 0.0);
+#endif
+
+#ifdef ALIGN_AXIS_LOCKED
+  let locked_right = cross(axis.xyz, to_camera);
+  if (length(locked_right) > 0.0001) {
+    up = axis.xyz;
+    right = normalize(locked_right);
+  }
+#endif
+
+#ifdef ALIGN_WORLD
+  right = quat_rotate(axis, vec3<f32>(1.0, 0.0, 0.0));
+  up = quat_rotate(axis, vec3<f32>(0.0, 1.0, 0.0));
+#endif
+
   let rotation = mat2x2<f32>(
     vec2<f32>(sin_cos.x, -sin_cos.y),
     vec2<f32>(sin_cos.y, sin_cos.x),
@@ -83,13 +163,19 @@ fn vs_main(model: VertexInput) -> VertexOutput {
 
   var world_space: vec3<f32> = 
     particle_position + 
-    (camera_right * vertex_position.x * size) + 
-    (camera_up * vertex_position.y * size);
+    (right * vertex_position.x * quad_size.x) + 
+    (up * vertex_position.y * quad_size.y);
 
   var out: VertexOutput;
   out.position = view.view_proj * vec4<f32>(world_space, 1.0);
   out.color = colors.data[particle_idx];
-  out.uv = uvs[vert_idx];
+
+  let frame_count = material.rows * material.columns;
+  let frame = max(frames.data[particle_idx], 0.0);
+  let current_frame = u32(floor(frame)) % frame_count;
+  out.uv = sheet_uv(uvs[vert_idx], current_frame);
+  out.next_uv = sheet_uv(uvs[vert_idx], (current_frame + 1u) % frame_count);
+  out.frame_blend = fract(frame);
   return out;
 }
 
@@ -97,7 +183,12 @@ fn vs_main(model: VertexInput) -> VertexOutput {
 fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
   var output_color: vec4<f32> = in.color;
   if ((material.flags & FLAGS_BASE_COLOR_TEXTURE_BIT) != 0u) {
-      output_color = output_color * textureSample(base_color_texture, base_color_sampler, in.uv);
+      var texture_color: vec4<f32> = textureSample(base_color_texture, base_color_sampler, in.uv);
+      if ((material.flags & FLAGS_FRAME_BLENDING_BIT) != 0u) {
+          let next_color = textureSample(base_color_texture, base_color_sampler, in.next_uv);
+          texture_color = mix(texture_color, next_color, in.frame_blend);
+      }
+      output_color = output_color * texture_color;
   }
   return output_color;
 }
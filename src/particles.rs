@@ -1,3 +1,6 @@
+use crate::collision::{
+    self, ParticleColliders, ParticleCollision, ParticleCollisions, WorldCollider,
+};
 use bevy::{
     math::*,
     prelude::*,
@@ -6,6 +9,67 @@ use bevy::{
 };
 use rand::{rngs::SmallRng, Rng, SeedableRng};
 
+/// A bitmask describing which layers a particle system belongs to.
+///
+/// Scene elements that act on particles, like force fields, only affect particle systems
+/// that share at least one layer with them. Particle systems without this component are
+/// treated as being on layer 0 only.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParticleLayers(pub u32);
+
+impl ParticleLayers {
+    /// A mask containing every layer.
+    pub const fn all() -> Self {
+        Self(u32::MAX)
+    }
+
+    /// A mask containing no layers.
+    pub const fn none() -> Self {
+        Self(0)
+    }
+
+    /// A mask containing only the given layer.
+    ///
+    /// # Panics
+    /// Panics if `layer` is 32 or larger.
+    pub const fn layer(layer: u8) -> Self {
+        Self(Self::bit(layer))
+    }
+
+    /// Adds a layer to the mask.
+    ///
+    /// # Panics
+    /// Panics if `layer` is 32 or larger.
+    pub const fn with(self, layer: u8) -> Self {
+        Self(self.0 | Self::bit(layer))
+    }
+
+    /// Removes a layer from the mask.
+    ///
+    /// # Panics
+    /// Panics if `layer` is 32 or larger.
+    pub const fn without(self, layer: u8) -> Self {
+        Self(self.0 & !Self::bit(layer))
+    }
+
+    /// Checks if the two masks share any layers.
+    #[inline]
+    pub const fn intersects(&self, other: &ParticleLayers) -> bool {
+        (self.0 & other.0) != 0
+    }
+
+    const fn bit(layer: u8) -> u32 {
+        assert!(layer < 32, "particle layers must be less than 32");
+        1 << layer
+    }
+}
+
+impl Default for ParticleLayers {
+    fn default() -> Self {
+        Self::layer(0)
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct ParticleParams {
     pub position: Vec3,
@@ -15,6 +79,8 @@ pub struct ParticleParams {
     pub angular_velocity: f32,
     pub color: Color,
     pub lifetime: f32,
+    /// The texture sheet frame to start on.
+    pub frame: f32,
 }
 
 #[derive(Debug, Clone)]
@@ -43,14 +109,26 @@ pub struct Particles {
     // X, Y, Z - world coordinates
     // W - 1D rotation
     pub(crate) positions: Vec<Vec4>,
+    // The position of each particle before it was last advanced.
+    pub(crate) previous_positions: Vec<Vec3>,
     pub(crate) colors: Vec<Vec4>,
+    // The color each particle was spawned with.
+    pub(crate) start_colors: Vec<Vec4>,
     // X, Y, Z - world coordinates
     // W - 1D rotation
     pub(crate) velocities: Vec<Vec4>,
     pub(crate) lerp_factors: Vec<f32>,
     pub(crate) sizes: Vec<f32>,
+    // The size each particle was spawned with.
+    pub(crate) start_sizes: Vec<f32>,
+    // Texture sheet frame, the fractional part is used for frame blending.
+    pub(crate) frames: Vec<f32>,
+    // The frame each particle was spawned with.
+    pub(crate) start_frames: Vec<f32>,
     pub(crate) starts: Vec<f32>,
     pub(crate) expirations: Vec<f32>,
+    // Previous positions of each particle, only recorded when enabled.
+    pub(crate) history: Option<ParticleHistory>,
     // TODO(james7132): make this user initializable.
     rng: SmallRng,
 }
@@ -67,16 +145,46 @@ impl Particles {
             capacity,
             lifetime: 0.0,
             positions: Vec::with_capacity(capacity),
+            previous_positions: Vec::with_capacity(capacity),
             colors: Vec::with_capacity(capacity),
+            start_colors: Vec::with_capacity(capacity),
             velocities: Vec::with_capacity(capacity),
             sizes: Vec::with_capacity(capacity),
+            start_sizes: Vec::with_capacity(capacity),
+            frames: Vec::with_capacity(capacity),
+            start_frames: Vec::with_capacity(capacity),
             lerp_factors: Vec::with_capacity(capacity),
             starts: Vec::with_capacity(capacity),
             expirations: Vec::with_capacity(capacity),
+            history: None,
             rng: SmallRng::from_entropy(),
         }
     }
 
+    /// Records up to `length` previous positions of every particle, which can be drawn as
+    /// ribbons with [`RibbonSource::History`](crate::RibbonSource::History).
+    ///
+    /// A new position is only recorded once a particle has moved at least `min_distance`
+    /// from the last recorded one.
+    pub fn with_history(mut self, length: usize, min_distance: f32) -> Self {
+        let mut history = ParticleHistory::new(length, min_distance);
+        history.reserve(self.positions.capacity());
+        for position in self.positions.iter() {
+            history.push(position.xyz());
+        }
+        self.history = Some(history);
+        self
+    }
+
+    /// Gets the recorded positions of a particle, from newest to oldest. Returns `None` if
+    /// history is not enabled.
+    ///
+    /// # Panics
+    /// Panics if the provided index is out of bounds.
+    pub fn history(&self, idx: usize) -> Option<impl Iterator<Item = Vec3> + '_> {
+        self.history.as_ref().map(|history| history.iter(idx))
+    }
+
     /// Gets a read-only reference to a particle.
     ///
     /// # Panics
@@ -111,13 +219,22 @@ impl Particles {
     pub fn spawn(&mut self, params: ParticleParams) {
         self.positions
             .push(Vec4::from((params.position, params.rotation)));
+        self.previous_positions.push(params.position);
         self.velocities
             .push(Vec4::from((params.velocity, params.angular_velocity)));
-        self.colors.push(params.color.as_rgba_f32().into());
+        let color = Vec4::from(params.color.as_rgba_f32());
+        self.colors.push(color);
+        self.start_colors.push(color);
         self.sizes.push(params.size);
+        self.start_sizes.push(params.size);
+        self.frames.push(params.frame);
+        self.start_frames.push(params.frame);
         self.lerp_factors.push(self.rng.gen_range(0.0..1.0));
         self.starts.push(self.lifetime);
         self.expirations.push(self.lifetime + params.lifetime);
+        if let Some(history) = self.history.as_mut() {
+            history.push(params.position);
+        }
     }
 
     /// Spawns a batch of particles with the given parameters.
@@ -130,16 +247,80 @@ impl Particles {
         }
     }
 
+    /// Removes a particle and returns its parameters, moving the last particle into its
+    /// place. The returned `lifetime` is the particle's remaining lifetime.
+    ///
+    /// # Panics
+    /// Panics if the provided index is out of bounds.
+    pub fn remove(&mut self, idx: usize) -> ParticleParams {
+        let position = self.positions[idx];
+        let velocity = self.velocities[idx];
+        let params = ParticleParams {
+            position: position.xyz(),
+            rotation: position.w,
+            size: self.sizes[idx],
+            velocity: velocity.xyz(),
+            angular_velocity: velocity.w,
+            color: Color::from(self.colors[idx]),
+            lifetime: self.expirations[idx] - self.lifetime,
+            frame: self.frames[idx],
+        };
+        let last = self.len() - 1;
+        // SAFE: idx was bounds checked above and last is the final valid index.
+        unsafe {
+            self.kill(idx, last);
+            self.flush(last);
+        }
+        params
+    }
+
+    /// Removes a particle so it can be moved into another system with
+    /// [`Particles::spawn_aged`], returning its parameters and age. Unlike
+    /// [`Particles::remove`], the color, size and frame are the ones it was spawned with, as
+    /// the modifiers of the new system are applied to them instead.
+    pub(crate) fn take(&mut self, idx: usize) -> (ParticleParams, f32) {
+        let age = self.lifetime - self.starts[idx];
+        let color = Color::from(self.start_colors[idx]);
+        let size = self.start_sizes[idx];
+        let frame = self.start_frames[idx];
+        let params = ParticleParams {
+            color,
+            size,
+            frame,
+            ..self.remove(idx)
+        };
+        (params, age)
+    }
+
+    /// Spawns a particle that has already lived for `age` seconds. `params.lifetime` is the
+    /// particle's remaining lifetime.
+    pub(crate) fn spawn_aged(&mut self, params: ParticleParams, age: f32) {
+        self.spawn(params);
+        let idx = self.len() - 1;
+        self.starts[idx] -= age;
+    }
+
     /// Consumes another Particles instance and merges in it's particles.
     pub fn merge(&mut self, batch: impl Into<Particles>) {
         let batch = batch.into();
+        let start = self.len();
         self.positions.extend(batch.positions);
+        self.previous_positions.extend(batch.previous_positions);
         self.velocities.extend(batch.velocities);
         self.colors.extend(batch.colors);
+        self.start_colors.extend(batch.start_colors);
         self.sizes.extend(batch.sizes);
+        self.start_sizes.extend(batch.start_sizes);
+        self.frames.extend(batch.frames);
+        self.start_frames.extend(batch.start_frames);
         self.lerp_factors.extend(batch.lerp_factors);
         self.starts.extend(batch.starts);
         self.expirations.extend(batch.expirations);
+        if let Some(history) = self.history.as_mut() {
+            for position in self.positions[start..].iter() {
+                history.push(position.xyz());
+            }
+        }
     }
 
     pub fn iter<'a>(&'a self) -> ParticleIter<'a> {
@@ -166,23 +347,47 @@ impl Particles {
 
     pub fn reserve(&mut self, capacity: usize) {
         self.positions.reserve(capacity);
+        self.previous_positions.reserve(capacity);
         self.sizes.reserve(capacity);
+        self.start_sizes.reserve(capacity);
+        self.frames.reserve(capacity);
+        self.start_frames.reserve(capacity);
         self.lerp_factors.reserve(capacity);
         self.velocities.reserve(capacity);
         self.colors.reserve(capacity);
+        self.start_colors.reserve(capacity);
         self.starts.reserve(capacity);
         self.expirations.reserve(capacity);
+        if let Some(history) = self.history.as_mut() {
+            history.reserve(capacity);
+        }
     }
 
     pub fn clear(&mut self) {
         self.lifetime = 0.0;
         self.positions.clear();
+        self.previous_positions.clear();
         self.sizes.clear();
+        self.start_sizes.clear();
+        self.frames.clear();
+        self.start_frames.clear();
         self.lerp_factors.clear();
         self.velocities.clear();
         self.colors.clear();
+        self.start_colors.clear();
         self.starts.clear();
         self.expirations.clear();
+        if let Some(history) = self.history.as_mut() {
+            history.truncate(0);
+        }
+    }
+
+    /// Resets the colors, sizes and frames of every particle to the values they were spawned
+    /// with, so the modifiers applied each frame start from the same values.
+    pub(crate) fn reset_derived(&mut self) {
+        self.colors.copy_from_slice(&self.start_colors);
+        self.sizes.copy_from_slice(&self.start_sizes);
+        self.frames.copy_from_slice(&self.start_frames);
     }
 
     pub fn compute_aabb(&self) -> Option<Aabb> {
@@ -196,7 +401,15 @@ impl Particles {
             min = position.min(min);
             max = position.max(max);
         }
-        Some(Aabb::from_min_max(min.xyz(), max.xyz()))
+        let (mut min, mut max) = (min.xyz(), max.xyz());
+        // Trails drawn through the recorded positions stay visible after their particles move.
+        if let Some(history) = self.history.as_ref() {
+            for point in history.points.iter() {
+                min = point.min(min);
+                max = point.max(max);
+            }
+        }
+        Some(Aabb::from_min_max(min, max))
     }
 
     /// Gets a ratio of how much of a particle's lifetime has passed. Will be 0.0 when the
@@ -212,6 +425,18 @@ impl Particles {
 
     #[inline(always)]
     pub fn advance_particles(&mut self, delta_time: f32) {
+        self.advance_particles_colliding(delta_time, &[], None);
+    }
+
+    /// Advances the particles while continuously colliding them against the provided
+    /// colliders. If `collisions` is provided, every collision is recorded into it.
+    #[inline(always)]
+    pub(crate) fn advance_particles_colliding(
+        &mut self,
+        delta_time: f32,
+        colliders: &[&WorldCollider],
+        mut collisions: Option<&mut Vec<ParticleCollision>>,
+    ) {
         self.lifetime += delta_time;
 
         if self.len() <= 0 {
@@ -230,7 +455,37 @@ impl Particles {
                     self.kill(idx, last);
                     last -= 1;
                 } else {
-                    self.positions[idx] += self.velocities[idx] * delta_time;
+                    let start = *self.positions.get_unchecked(idx);
+                    *self.previous_positions.get_unchecked_mut(idx) = start.xyz();
+                    let velocity = *self.velocities.get_unchecked(idx);
+                    let mut end = start + velocity * delta_time;
+                    if !colliders.is_empty() {
+                        if let Some(hit) =
+                            collision::collide(colliders, start.xyz(), end.xyz(), velocity.xyz())
+                        {
+                            if let Some(collisions) = collisions.as_mut() {
+                                collisions.push(ParticleCollision {
+                                    collider: hit.collider,
+                                    position: hit.position,
+                                    normal: hit.normal,
+                                    velocity: velocity.xyz(),
+                                    killed: hit.response.kill,
+                                });
+                            }
+                            if hit.response.kill {
+                                self.kill(idx, last);
+                                last -= 1;
+                                continue;
+                            }
+                            end = Vec4::from((hit.position, end.w));
+                            *self.velocities.get_unchecked_mut(idx) =
+                                Vec4::from((hit.velocity, velocity.w));
+                            let start_time = *self.starts.get_unchecked(idx);
+                            let expiration = self.expirations.get_unchecked_mut(idx);
+                            *expiration -= (*expiration - start_time) * hit.response.lifetime_loss;
+                        }
+                    }
+                    *self.positions.get_unchecked_mut(idx) = end;
                     idx += 1;
                 }
             }
@@ -241,29 +496,123 @@ impl Particles {
                 self.flush(last + 1);
             }
         }
+
+        if let Some(history) = self.history.as_mut() {
+            history.record(&self.positions);
+        }
     }
 
     #[inline(always)]
     unsafe fn kill(&mut self, idx: usize, end: usize) {
         debug_assert!(idx <= end);
         *self.positions.get_unchecked_mut(idx) = *self.positions.get_unchecked(end);
+        *self.previous_positions.get_unchecked_mut(idx) =
+            *self.previous_positions.get_unchecked(end);
         *self.velocities.get_unchecked_mut(idx) = *self.velocities.get_unchecked(end);
         *self.colors.get_unchecked_mut(idx) = *self.colors.get_unchecked(end);
+        *self.start_colors.get_unchecked_mut(idx) = *self.start_colors.get_unchecked(end);
         *self.sizes.get_unchecked_mut(idx) = *self.sizes.get_unchecked(end);
+        *self.start_sizes.get_unchecked_mut(idx) = *self.start_sizes.get_unchecked(end);
+        *self.frames.get_unchecked_mut(idx) = *self.frames.get_unchecked(end);
+        *self.start_frames.get_unchecked_mut(idx) = *self.start_frames.get_unchecked(end);
         *self.lerp_factors.get_unchecked_mut(idx) = *self.lerp_factors.get_unchecked(end);
         *self.starts.get_unchecked_mut(idx) = *self.starts.get_unchecked(end);
         *self.expirations.get_unchecked_mut(idx) = *self.expirations.get_unchecked(end);
+        if let Some(history) = self.history.as_mut() {
+            history.copy(end, idx);
+        }
     }
 
     #[inline(always)]
     unsafe fn flush(&mut self, len: usize) {
         self.positions.set_len(len);
+        self.previous_positions.set_len(len);
         self.velocities.set_len(len);
         self.colors.set_len(len);
+        self.start_colors.set_len(len);
         self.sizes.set_len(len);
+        self.start_sizes.set_len(len);
+        self.frames.set_len(len);
+        self.start_frames.set_len(len);
         self.lerp_factors.set_len(len);
         self.starts.set_len(len);
         self.expirations.set_len(len);
+        if let Some(history) = self.history.as_mut() {
+            history.truncate(len);
+        }
+    }
+}
+
+/// Fixed size ring buffers of previously recorded positions, one per particle.
+#[derive(Debug, Clone)]
+pub(crate) struct ParticleHistory {
+    length: usize,
+    min_distance: f32,
+    points: Vec<Vec3>,
+    // The index of the newest recorded position in each particle's ring buffer.
+    heads: Vec<u32>,
+    counts: Vec<u32>,
+}
+
+impl ParticleHistory {
+    fn new(length: usize, min_distance: f32) -> Self {
+        Self {
+            length: length.max(1),
+            min_distance,
+            points: Vec::new(),
+            heads: Vec::new(),
+            counts: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, position: Vec3) {
+        self.points
+            .extend(std::iter::repeat(position).take(self.length));
+        self.heads.push(0);
+        self.counts.push(1);
+    }
+
+    fn record(&mut self, positions: &[Vec4]) {
+        for (idx, position) in positions.iter().enumerate() {
+            let block = idx * self.length;
+            let head = self.heads[idx] as usize;
+            let position = position.xyz();
+            if self.points[block + head].distance(position) < self.min_distance {
+                continue;
+            }
+            let head = (head + 1) % self.length;
+            self.points[block + head] = position;
+            self.heads[idx] = head as u32;
+            self.counts[idx] = (self.counts[idx] + 1).min(self.length as u32);
+        }
+    }
+
+    fn copy(&mut self, src: usize, dst: usize) {
+        let length = self.length;
+        self.points
+            .copy_within(src * length..(src + 1) * length, dst * length);
+        self.heads[dst] = self.heads[src];
+        self.counts[dst] = self.counts[src];
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.points.truncate(len * self.length);
+        self.heads.truncate(len);
+        self.counts.truncate(len);
+    }
+
+    fn reserve(&mut self, capacity: usize) {
+        self.points.reserve(capacity * self.length);
+        self.heads.reserve(capacity);
+        self.counts.reserve(capacity);
+    }
+
+    /// Iterates over a particle's recorded positions, from newest to oldest.
+    pub(crate) fn iter(&self, idx: usize) -> impl Iterator<Item = Vec3> + '_ {
+        let block = idx * self.length;
+        let head = self.heads[idx] as usize;
+        (0..self.counts[idx] as usize)
+            .map(move |offset| self.points[block + (head + self.length - offset) % self.length])
     }
 }
 
@@ -313,13 +662,172 @@ impl<'a> Iterator for ParticleIterMut<'a> {
     }
 }
 
-pub fn update_particles(
-    time: Res<Time>,
+pub fn reset_particles(
     compute_task_pool: Res<ComputeTaskPool>,
     mut particles: Query<&mut Particles>,
 ) {
-    let delta_time = time.delta_seconds_f64() as f32;
     particles.par_for_each_mut(&compute_task_pool, 8, |mut particles| {
-        particles.advance_particles(delta_time);
+        particles.reset_derived();
     });
 }
+
+pub fn update_particles(
+    time: Res<Time>,
+    compute_task_pool: Res<ComputeTaskPool>,
+    colliders: Res<ParticleColliders>,
+    mut particles: Query<(
+        &mut Particles,
+        Option<&ParticleLayers>,
+        Option<&mut ParticleCollisions>,
+    )>,
+) {
+    let delta_time = time.delta_seconds_f64() as f32;
+    particles.par_for_each_mut(
+        &compute_task_pool,
+        8,
+        |(mut particles, layers, collisions)| {
+            let colliders = colliders.filter(&layers.copied().unwrap_or_default());
+            if let Some(mut collisions) = collisions {
+                collisions.collisions.clear();
+                particles.advance_particles_colliding(
+                    delta_time,
+                    &colliders,
+                    Some(&mut collisions.collisions),
+                );
+            } else {
+                particles.advance_particles_colliding(delta_time, &colliders, None);
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision::{ColliderGeometry, ColliderShape, CollisionResponse};
+
+    fn history(history: &ParticleHistory, idx: usize) -> Vec<Vec3> {
+        history.iter(idx).collect()
+    }
+
+    fn positions(points: &[Vec3]) -> Vec<Vec4> {
+        points
+            .iter()
+            .map(|point| Vec4::from((*point, 0.0)))
+            .collect()
+    }
+
+    #[test]
+    fn history_records_newest_first() {
+        let mut history = ParticleHistory::new(3, 0.0);
+        history.push(Vec3::ZERO);
+        assert_eq!(self::history(&history, 0), vec![Vec3::ZERO]);
+
+        history.record(&positions(&[Vec3::X]));
+        assert_eq!(self::history(&history, 0), vec![Vec3::X, Vec3::ZERO]);
+
+        // The oldest position is overwritten once the ring buffer is full.
+        history.record(&positions(&[Vec3::Y]));
+        history.record(&positions(&[Vec3::Z]));
+        assert_eq!(self::history(&history, 0), vec![Vec3::Z, Vec3::Y, Vec3::X]);
+    }
+
+    #[test]
+    fn history_skips_points_within_min_distance() {
+        let mut history = ParticleHistory::new(4, 1.0);
+        history.push(Vec3::ZERO);
+        history.record(&positions(&[Vec3::X * 0.5]));
+        assert_eq!(self::history(&history, 0), vec![Vec3::ZERO]);
+
+        history.record(&positions(&[Vec3::X * 1.5]));
+        assert_eq!(self::history(&history, 0), vec![Vec3::X * 1.5, Vec3::ZERO]);
+    }
+
+    #[test]
+    fn history_copy_and_truncate() {
+        let mut history = ParticleHistory::new(2, 0.0);
+        history.push(Vec3::ZERO);
+        history.push(Vec3::ONE);
+        history.record(&positions(&[Vec3::X, Vec3::Y]));
+        history.record(&positions(&[Vec3::X * 2.0, Vec3::Y * 2.0]));
+
+        history.copy(1, 0);
+        history.truncate(1);
+        assert_eq!(self::history(&history, 0), vec![Vec3::Y * 2.0, Vec3::Y]);
+        assert_eq!(history.points.len(), 2);
+        assert_eq!(history.heads.len(), 1);
+        assert_eq!(history.counts.len(), 1);
+    }
+
+    #[test]
+    fn history_follows_killed_particles() {
+        let mut particles = Particles::new(3).with_history(4, 0.0);
+        for (velocity, lifetime) in [(Vec3::X, 0.5), (Vec3::Y, 10.0), (Vec3::Z, 10.0)] {
+            particles.spawn(ParticleParams {
+                velocity,
+                lifetime,
+                ..Default::default()
+            });
+        }
+        particles.advance_particles(1.0);
+
+        // The last particle is swapped into the place of the expired one.
+        assert_eq!(particles.len(), 2);
+        let history = |idx| particles.history(idx).unwrap().collect::<Vec<_>>();
+        assert_eq!(history(0), vec![Vec3::Z, Vec3::ZERO]);
+        assert_eq!(history(1), vec![Vec3::Y, Vec3::ZERO]);
+    }
+
+    #[test]
+    fn aabb_includes_history() {
+        let mut particles = Particles::new(2).with_history(4, 0.0);
+        for _ in 0..2 {
+            particles.spawn(ParticleParams {
+                position: Vec3::new(-1.0, 0.0, 0.0),
+                velocity: Vec3::X,
+                lifetime: 10.0,
+                ..Default::default()
+            });
+        }
+        particles.advance_particles(2.0);
+
+        let aabb = particles.compute_aabb().unwrap();
+        assert_eq!(Vec3::from(aabb.min()), Vec3::new(-1.0, 0.0, 0.0));
+        assert_eq!(Vec3::from(aabb.max()), Vec3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn collisions_remove_lifetime() {
+        let plane = WorldCollider::new(
+            Entity::from_raw(0),
+            Mat4::IDENTITY,
+            ParticleLayers::default(),
+            CollisionResponse {
+                lifetime_loss: 0.25,
+                ..Default::default()
+            },
+            ColliderGeometry::Shape(ColliderShape::Plane),
+        );
+        let mut particles = Particles::new(2);
+        particles.spawn(ParticleParams {
+            position: Vec3::Y * 0.05,
+            velocity: -Vec3::Y,
+            lifetime: 2.0,
+            ..Default::default()
+        });
+        particles.spawn(ParticleParams {
+            position: Vec3::Y,
+            velocity: Vec3::Y,
+            lifetime: 2.0,
+            ..Default::default()
+        });
+        let mut collisions = Vec::new();
+        particles.advance_particles_colliding(0.1, &[&plane], Some(&mut collisions));
+
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].normal, Vec3::Y);
+        assert_eq!(particles.expirations, vec![1.5, 2.0]);
+        assert!(particles.positions[0].y > 0.0);
+        assert!(particles.velocities[0].y > 0.0);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{
+    emitter::{EmitterContext, EmitterModifier},
+    particles::ParticleParams,
+};
+use bevy::math::*;
+use std::f32::consts::TAU;
+
+/// Moves a particle to `local`, in the emitting transform's space.
+fn place(particle: &mut ParticleParams, ctx: &EmitterContext, local: Vec3) {
+    particle.position = ctx.transform.mul_vec3(local);
+}
+
+/// Turns a particle's velocity along `local`, in the emitting transform's space, keeping its
+/// speed.
+fn redirect(particle: &mut ParticleParams, ctx: &EmitterContext, local: Vec3) {
+    let speed = particle.velocity.length();
+    particle.velocity = (ctx.transform.rotation * local).normalize_or_zero() * speed;
+}
+
+/// Spaces the particles of each group evenly around a circle in the XZ plane.
+#[derive(Debug, Clone)]
+pub struct RingPattern {
+    pub radius: f32,
+    /// If true, particles travel outward from the center of the ring.
+    pub radial_velocity: bool,
+}
+
+impl EmitterModifier for RingPattern {
+    fn modify(&mut self, particle: &mut ParticleParams, ctx: &mut EmitterContext) {
+        let angle = TAU * ctx.index as f32 / ctx.count.max(1) as f32;
+        let radial = Vec3::new(angle.cos(), 0.0, angle.sin());
+        place(particle, ctx, radial * self.radius);
+        if self.radial_velocity {
+            redirect(particle, ctx, radial);
+        }
+    }
+}
+
+/// Spaces the particles of each group evenly along the arms of a spiral in the XZ plane that
+/// rotates over the emitter's elapsed time.
+///
+/// Particles are dealt to the arms in turn, and each arm's particles are spread from the
+/// center out to `radius`.
+#[derive(Debug, Clone)]
+pub struct SpiralPattern {
+    pub radius: f32,
+    pub arms: u32,
+    /// How many times each arm winds around the center between the center and `radius`.
+    pub turns: f32,
+    /// How fast the spiral turns, in radians per second.
+    pub angular_velocity: f32,
+    /// If true, particles travel outward along their arm.
+    pub radial_velocity: bool,
+}
+
+impl EmitterModifier for SpiralPattern {
+    fn modify(&mut self, particle: &mut ParticleParams, ctx: &mut EmitterContext) {
+        let arms = self.arms.max(1) as usize;
+        let arm = ctx.index % arms;
+        let per_arm = (ctx.count.max(1) + arms - 1) / arms;
+        let t = (ctx.index / arms + 1) as f32 / per_arm as f32;
+        let winding = TAU * self.turns;
+        let angle =
+            TAU * arm as f32 / arms as f32 + winding * t + ctx.elapsed * self.angular_velocity;
+        let radial = Vec3::new(angle.cos(), 0.0, angle.sin());
+        place(particle, ctx, radial * self.radius * t);
+        if self.radial_velocity {
+            // The arm's direction is its outward motion plus the motion from winding.
+            let tangent = Vec3::new(-angle.sin(), 0.0, angle.cos());
+            redirect(particle, ctx, radial + tangent * winding * t);
+        }
+    }
+}
+
+/// Places the particles of each group on the cells of a grid centered on the emitter,
+/// filling X first, then Z, then Y. Groups larger than the grid wrap around.
+#[derive(Debug, Clone)]
+pub struct GridPattern {
+    /// The number of cells along each axis.
+    pub cells: UVec3,
+    /// The distance between neighbouring cells along each axis.
+    pub spacing: Vec3,
+}
+
+impl EmitterModifier for GridPattern {
+    fn modify(&mut self, particle: &mut ParticleParams, ctx: &mut EmitterContext) {
+        let cells = self.cells.max(UVec3::ONE);
+        let idx = ctx.index as u32 % (cells.x * cells.y * cells.z);
+        let cell = UVec3::new(
+            idx % cells.x,
+            idx / (cells.x * cells.z),
+            idx / cells.x % cells.z,
+        );
+        let offset = (cell.as_vec3() - (cells - UVec3::ONE).as_vec3() * 0.5) * self.spacing;
+        place(particle, ctx, offset);
+    }
+}
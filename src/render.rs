@@ -1,6 +1,8 @@
 use crate::{
+    beam::ParticleBeam,
     material::{ParticleMaterial, ParticleMaterialUniformData},
     particles::Particles,
+    ribbon::{ParticleRibbon, RibbonGeometry},
 };
 use bevy::{
     app::prelude::*,
@@ -35,38 +37,105 @@ use wgpu::{
 
 pub const PARTICLE_SHADER_HANDLE: HandleUntyped =
     HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 3032357527543835453);
+pub const RIBBON_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 7482390165378312107);
+
+/// How the quads of a particle system's billboards are oriented.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub enum BillboardAlignment {
+    /// Parallel to the camera's view plane.
+    ViewPlane,
+    /// Turned to face the camera's position, keeping the world's Y axis up.
+    FacingCamera,
+    /// Stretched along each particle's velocity. The quad's length is the particle's size
+    /// times `length_scale`, plus its speed times `speed_scale`.
+    Velocity { speed_scale: f32, length_scale: f32 },
+    /// Only rotates around a fixed world axis to face the camera, like grass or trees.
+    AxisLocked(Vec3),
+    /// Fixed in world space, with the quad's X and Y axes rotated by the given rotation.
+    World(Quat),
+}
+
+impl BillboardAlignment {
+    /// Quads lying flat on the XZ plane, facing up, for ground decals.
+    pub fn horizontal() -> Self {
+        Self::World(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2))
+    }
+
+    /// Upright quads that only turn around the world's Y axis.
+    pub fn cylindrical() -> Self {
+        Self::AxisLocked(Vec3::Y)
+    }
+
+    fn key(&self) -> ParticlePipelineKey {
+        match self {
+            Self::ViewPlane => ParticlePipelineKey::VIEW_PLANE,
+            Self::FacingCamera => ParticlePipelineKey::FACING_CAMERA,
+            Self::Velocity { .. } => ParticlePipelineKey::VELOCITY,
+            Self::AxisLocked(_) => ParticlePipelineKey::AXIS_LOCKED,
+            Self::World(_) => ParticlePipelineKey::WORLD,
+        }
+    }
+
+    /// The constants read by the shader, shared by every particle in the system.
+    fn axis(&self) -> Vec4 {
+        match *self {
+            Self::ViewPlane | Self::FacingCamera => Vec4::ZERO,
+            Self::Velocity {
+                speed_scale,
+                length_scale,
+            } => Vec4::new(speed_scale, length_scale, 0.0, 0.0),
+            Self::AxisLocked(axis) => Vec4::from((axis.normalize_or_zero(), 0.0)),
+            Self::World(rotation) => Vec4::from(rotation),
+        }
+    }
+}
+
+impl Default for BillboardAlignment {
+    fn default() -> Self {
+        Self::ViewPlane
+    }
+}
 
 pub struct ParticleRenderPlugin;
 
 impl Plugin for ParticleRenderPlugin {
     fn build(&self, app: &mut App) {
         let particle_shader = Shader::from_wgsl(include_str!("particle.wgsl"));
+        let ribbon_shader = Shader::from_wgsl(include_str!("ribbon.wgsl"));
         app.add_system_to_stage(
             CoreStage::PostUpdate,
             compute_particles_aabb.label(VisibilitySystems::CalculateBounds),
         );
-        app.world
-            .get_resource_mut::<Assets<Shader>>()
-            .unwrap()
-            .set_untracked(PARTICLE_SHADER_HANDLE, particle_shader);
+        let mut shaders = app.world.get_resource_mut::<Assets<Shader>>().unwrap();
+        shaders.set_untracked(PARTICLE_SHADER_HANDLE, particle_shader);
+        shaders.set_untracked(RIBBON_SHADER_HANDLE, ribbon_shader);
         let render_app = app.get_sub_app(RenderApp).unwrap();
         render_app
             .add_system_to_stage(RenderStage::Extract, extract_particles)
             .add_system_to_stage(RenderStage::Prepare, prepare_particles)
             .add_system_to_stage(RenderStage::Queue, queue_particles)
+            .add_system_to_stage(RenderStage::Extract, extract_ribbons)
+            .add_system_to_stage(RenderStage::Prepare, prepare_ribbons)
+            .add_system_to_stage(RenderStage::Queue, queue_ribbons)
             .init_resource::<ParticlePipeline>()
             .init_resource::<ParticleMeta>()
             .init_resource::<ExtractedParticles>()
             .init_resource::<MaterialBindGroups>()
-            .init_resource::<SpecializedPipelines<ParticlePipeline>>();
+            .init_resource::<SpecializedPipelines<ParticlePipeline>>()
+            .init_resource::<RibbonPipeline>()
+            .init_resource::<RibbonMeta>()
+            .init_resource::<ExtractedRibbons>()
+            .init_resource::<SpecializedPipelines<RibbonPipeline>>();
 
         let draw_particle = DrawParticle::new(&mut render_app.world);
-        render_app
+        let draw_ribbon = DrawRibbon::new(&mut render_app.world);
+        let draw_functions = render_app
             .world
             .get_resource::<DrawFunctions<Transparent3d>>()
-            .unwrap()
-            .write()
-            .add(draw_particle);
+            .unwrap();
+        draw_functions.write().add(draw_particle);
+        draw_functions.write().add(draw_ribbon);
     }
 }
 
@@ -77,6 +146,8 @@ struct ParticlePipeline {
 
     // This dummy white texture is to be used in place of optional StandardMaterial textures
     dummy_white_gpu_image: GpuImage,
+    // Bound in place of the velocities when no visible system is aligned to its velocity.
+    dummy_velocities: Buffer,
 }
 
 impl FromWorld for ParticlePipeline {
@@ -133,6 +204,32 @@ impl FromWorld for ParticlePipeline {
                     },
                     count: None,
                 },
+                // Texture Sheet Frames
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::VERTEX,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: BufferSize::new(std::mem::size_of::<f32>() as u64),
+                    },
+                    count: None,
+                },
+                // Velocities
+                storage_layout_entry(4, std::mem::size_of::<Vec4>()),
+                // Alignment
+                BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: ShaderStages::VERTEX,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: BufferSize::new(
+                            ParticleAlignmentUniform::std140_size_static() as u64,
+                        ),
+                    },
+                    count: None,
+                },
             ],
         });
 
@@ -141,7 +238,7 @@ impl FromWorld for ParticlePipeline {
             entries: &[
                 BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: ShaderStages::FRAGMENT,
+                    visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
                     ty: BindingType::Buffer {
                         ty: BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -214,87 +311,194 @@ impl FromWorld for ParticlePipeline {
             }
         };
 
+        let dummy_velocities = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("particle_dummy_velocity_buffer"),
+            contents: bytemuck::bytes_of(&Vec4::ZERO),
+            usage: BufferUsages::STORAGE,
+        });
+
         Self {
             view_layout,
             particle_layout,
             material_layout,
 
             dummy_white_gpu_image,
+            dummy_velocities,
         }
     }
 }
 
-#[derive(Clone, Eq, Hash, PartialEq)]
-struct ParticlePipelineKey;
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+struct ParticlePipelineKey(u32);
+
+impl ParticlePipelineKey {
+    const VIEW_PLANE: Self = Self(0);
+    const FACING_CAMERA: Self = Self(1);
+    const VELOCITY: Self = Self(2);
+    const AXIS_LOCKED: Self = Self(3);
+    const WORLD: Self = Self(4);
+
+    fn shader_defs(&self) -> Vec<String> {
+        let def = match *self {
+            Self::FACING_CAMERA => "ALIGN_FACING_CAMERA",
+            Self::VELOCITY => "ALIGN_VELOCITY",
+            Self::AXIS_LOCKED => "ALIGN_AXIS_LOCKED",
+            Self::WORLD => "ALIGN_WORLD",
+            _ => return vec![],
+        };
+        vec![def.to_string()]
+    }
+}
 
 impl SpecializedPipeline for ParticlePipeline {
     type Key = ParticlePipelineKey;
 
-    fn specialize(&self, _: Self::Key) -> RenderPipelineDescriptor {
-        RenderPipelineDescriptor {
-            label: Some("particle_render_pipeline".into()),
-            vertex: VertexState {
-                shader: PARTICLE_SHADER_HANDLE.typed::<Shader>(),
-                entry_point: "vs_main".into(),
-                buffers: vec![],
-                shader_defs: vec![],
-            },
-            fragment: Some(FragmentState {
-                shader: PARTICLE_SHADER_HANDLE.typed::<Shader>(),
-                shader_defs: vec![],
-                entry_point: "fs_main".into(),
-                targets: vec![ColorTargetState {
-                    format: TextureFormat::bevy_default(),
-                    blend: Some(BlendState {
-                        color: BlendComponent {
-                            src_factor: BlendFactor::SrcAlpha,
-                            dst_factor: BlendFactor::OneMinusSrcAlpha,
-                            operation: BlendOperation::Add,
-                        },
-                        alpha: BlendComponent {
-                            src_factor: BlendFactor::One,
-                            dst_factor: BlendFactor::One,
-                            operation: BlendOperation::Add,
-                        },
-                    }),
-                    write_mask: ColorWrites::ALL,
-                }],
-            }),
-            depth_stencil: Some(DepthStencilState {
-                format: TextureFormat::Depth32Float,
-                depth_write_enabled: false,
-                depth_compare: CompareFunction::Greater,
-                stencil: StencilState {
-                    front: StencilFaceState::IGNORE,
-                    back: StencilFaceState::IGNORE,
-                    read_mask: 0,
-                    write_mask: 0,
-                },
-                bias: DepthBiasState {
-                    constant: 0,
-                    slope_scale: 0.0,
-                    clamp: 0.0,
-                },
-            }),
-            layout: Some(vec![
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        transparent_pipeline_descriptor(
+            "particle_render_pipeline",
+            PARTICLE_SHADER_HANDLE.typed::<Shader>(),
+            key.shader_defs(),
+            vec![
                 self.view_layout.clone(),
                 self.particle_layout.clone(),
                 self.material_layout.clone(),
-            ]),
-            multisample: MultisampleState::default(),
-            primitive: PrimitiveState {
-                topology: PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: FrontFace::Ccw,
-                cull_mode: None,
-                polygon_mode: PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
+            ],
+        )
+    }
+}
+
+struct RibbonPipeline {
+    view_layout: BindGroupLayout,
+    ribbon_layout: BindGroupLayout,
+    material_layout: BindGroupLayout,
+}
+
+impl FromWorld for RibbonPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let particle_pipeline = world.get_resource::<ParticlePipeline>().unwrap();
+        let render_device = world.get_resource::<RenderDevice>().unwrap();
+
+        let ribbon_layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                // Positions/U Coordinates
+                storage_layout_entry(0, std::mem::size_of::<Vec4>()),
+                // Tangents/Half Widths
+                storage_layout_entry(1, std::mem::size_of::<Vec4>()),
+                // Colors
+                storage_layout_entry(2, std::mem::size_of::<Vec4>()),
+                // Segments
+                storage_layout_entry(3, std::mem::size_of::<u32>()),
+            ],
+        });
+
+        Self {
+            view_layout: particle_pipeline.view_layout.clone(),
+            ribbon_layout,
+            material_layout: particle_pipeline.material_layout.clone(),
         }
     }
 }
 
+#[derive(Clone, Eq, Hash, PartialEq)]
+struct RibbonPipelineKey;
+
+impl SpecializedPipeline for RibbonPipeline {
+    type Key = RibbonPipelineKey;
+
+    fn specialize(&self, _: Self::Key) -> RenderPipelineDescriptor {
+        transparent_pipeline_descriptor(
+            "ribbon_render_pipeline",
+            RIBBON_SHADER_HANDLE.typed::<Shader>(),
+            vec![],
+            vec![
+                self.view_layout.clone(),
+                self.ribbon_layout.clone(),
+                self.material_layout.clone(),
+            ],
+        )
+    }
+}
+
+fn storage_layout_entry(binding: u32, min_size: usize) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::VERTEX,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: BufferSize::new(min_size as u64),
+        },
+        count: None,
+    }
+}
+
+/// An alpha blended pipeline that reads its vertices from storage buffers.
+fn transparent_pipeline_descriptor(
+    label: &'static str,
+    shader: Handle<Shader>,
+    shader_defs: Vec<String>,
+    layout: Vec<BindGroupLayout>,
+) -> RenderPipelineDescriptor {
+    RenderPipelineDescriptor {
+        label: Some(label.into()),
+        vertex: VertexState {
+            shader: shader.clone(),
+            entry_point: "vs_main".into(),
+            buffers: vec![],
+            shader_defs: shader_defs.clone(),
+        },
+        fragment: Some(FragmentState {
+            shader,
+            shader_defs,
+            entry_point: "fs_main".into(),
+            targets: vec![ColorTargetState {
+                format: TextureFormat::bevy_default(),
+                blend: Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::SrcAlpha,
+                        dst_factor: BlendFactor::OneMinusSrcAlpha,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: BlendComponent {
+                        src_factor: BlendFactor::One,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
+                    },
+                }),
+                write_mask: ColorWrites::ALL,
+            }],
+        }),
+        depth_stencil: Some(DepthStencilState {
+            format: TextureFormat::Depth32Float,
+            depth_write_enabled: false,
+            depth_compare: CompareFunction::Greater,
+            stencil: StencilState {
+                front: StencilFaceState::IGNORE,
+                back: StencilFaceState::IGNORE,
+                read_mask: 0,
+                write_mask: 0,
+            },
+            bias: DepthBiasState {
+                constant: 0,
+                slope_scale: 0.0,
+                clamp: 0.0,
+            },
+        }),
+        layout: Some(layout),
+        multisample: MultisampleState::default(),
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+    }
+}
+
 fn compute_particles_aabb(
     compute_task_pool: Res<ComputeTaskPool>,
     mut query: Query<(&mut Aabb, &Particles)>,
@@ -308,10 +512,27 @@ fn compute_particles_aabb(
 
 struct ExtractedParticle {
     material: Handle<ParticleMaterial>,
+    key: ParticlePipelineKey,
+    axis: Vec4,
 
     positions: Vec<Vec4>,
     sizes: Vec<f32>,
     colors: Vec<Vec4>,
+    frames: Vec<f32>,
+    // Only extracted for systems aligned to their velocity.
+    velocities: Option<Vec<Vec4>>,
+}
+
+/// The alignment constants of a batch of particles.
+#[derive(Clone, AsStd140)]
+struct ParticleAlignmentUniform {
+    // Velocity - X: speed scale, Y: length scale
+    // AxisLocked - X, Y, Z: the locked axis
+    // World - the rotation of the quads
+    axis: Vec4,
+    // Maps the index of a particle in the batch to its index in the velocity buffer.
+    first_particle: u32,
+    first_velocity: u32,
 }
 
 #[derive(Default, Component)]
@@ -323,13 +544,21 @@ fn extract_particles(
     mut render_world: ResMut<RenderWorld>,
     materials: Res<Assets<ParticleMaterial>>,
     images: Res<Assets<Image>>,
-    query: Query<(&ComputedVisibility, &Particles, &Handle<ParticleMaterial>)>,
+    query: Query<
+        (
+            &ComputedVisibility,
+            &Particles,
+            &Handle<ParticleMaterial>,
+            Option<&BillboardAlignment>,
+        ),
+        Without<ParticleRibbon>,
+    >,
 ) {
     let mut extracted_particles = render_world
         .get_resource_mut::<ExtractedParticles>()
         .unwrap();
     extracted_particles.particles.clear();
-    for (visible, particles, material_handle) in query.iter() {
+    for (visible, particles, material_handle, alignment) in query.iter() {
         if !visible.is_visible {
             continue;
         }
@@ -340,12 +569,18 @@ fn extract_particles(
                 }
             }
 
+            let alignment = alignment.copied().unwrap_or_default();
             // TODO(james7132): Find a way to do this without
             extracted_particles.particles.push(ExtractedParticle {
                 material: material_handle.clone_weak(),
+                key: alignment.key(),
+                axis: alignment.axis(),
                 positions: particles.positions.clone(),
                 sizes: particles.sizes.clone(),
                 colors: particles.colors.clone(),
+                frames: particles.frames.clone(),
+                velocities: matches!(alignment, BillboardAlignment::Velocity { .. })
+                    .then(|| particles.velocities.clone()),
             });
         }
     }
@@ -360,6 +595,10 @@ struct ParticleMeta {
     positions: BufferVec<Vec4>,
     sizes: BufferVec<f32>,
     colors: BufferVec<Vec4>,
+    frames: BufferVec<f32>,
+    velocities: BufferVec<Vec4>,
+    velocity_count: u64,
+    alignments: DynamicUniformVec<ParticleAlignmentUniform>,
 }
 
 impl Default for ParticleMeta {
@@ -373,6 +612,10 @@ impl Default for ParticleMeta {
             positions: BufferVec::new(BufferUsages::STORAGE),
             sizes: BufferVec::new(BufferUsages::STORAGE),
             colors: BufferVec::new(BufferUsages::STORAGE),
+            frames: BufferVec::new(BufferUsages::STORAGE),
+            velocities: BufferVec::new(BufferUsages::STORAGE),
+            velocity_count: 0,
+            alignments: DynamicUniformVec::default(),
         }
     }
 }
@@ -387,17 +630,23 @@ fn prepare_particles(
     particle_meta.positions.clear();
     particle_meta.sizes.clear();
     particle_meta.colors.clear();
+    particle_meta.frames.clear();
+    particle_meta.velocities.clear();
+    particle_meta.alignments.clear();
 
     extracted_particles
         .particles
-        .sort_by(|a, b| a.material.cmp(&b.material));
+        .sort_by(|a, b| (a.key, &a.material).cmp(&(b.key, &b.material)));
 
     let mut total_count = 0;
+    let mut velocity_count = 0;
     for particle in extracted_particles.particles.iter() {
         total_count += particle.positions.len();
+        velocity_count += particle.velocities.as_ref().map_or(0, Vec::len);
     }
 
     particle_meta.total_count = total_count as u64;
+    particle_meta.velocity_count = velocity_count as u64;
     particle_meta.ranges.clear();
     if total_count == 0 {
         return;
@@ -406,32 +655,60 @@ fn prepare_particles(
     particle_meta.positions.reserve(total_count, &render_device);
     particle_meta.sizes.reserve(total_count, &render_device);
     particle_meta.colors.reserve(total_count, &render_device);
+    particle_meta.frames.reserve(total_count, &render_device);
+    particle_meta
+        .velocities
+        .reserve(velocity_count, &render_device);
 
+    // Batches are split whenever the material, the alignment or its constants change.
     let mut start: u32 = 0;
     let mut end: u32 = 0;
-    let mut current_batch_handle: Option<Handle<ParticleMaterial>> = None;
+    let mut first_velocity: u32 = 0;
+    let mut velocity_end: u32 = 0;
+    let mut current_batch: Option<(ParticlePipelineKey, Handle<ParticleMaterial>, Vec4)> = None;
     for particle in extracted_particles.particles.iter() {
-        batch_copy(&particle.positions, &mut particle_meta.positions);
-        batch_copy(&particle.sizes, &mut particle_meta.sizes);
-        batch_copy(&particle.colors, &mut particle_meta.colors);
-        end += particle.positions.len() as u32;
-        if let Some(current_batch_handle) = &current_batch_handle {
-            if *current_batch_handle != particle.material {
+        if let Some((key, handle, axis)) = &current_batch {
+            if *key != particle.key || *handle != particle.material || *axis != particle.axis {
+                let alignment_offset = particle_meta.alignments.push(ParticleAlignmentUniform {
+                    axis: *axis,
+                    first_particle: start,
+                    first_velocity,
+                });
                 commands.spawn_bundle((ParticleBatch {
                     range: start..end,
-                    handle: current_batch_handle.clone_weak(),
+                    handle: handle.clone_weak(),
+                    key: *key,
+                    alignment_offset,
                 },));
+                start = end;
+                first_velocity = velocity_end;
             }
-            start = end;
         }
-        current_batch_handle = Some(particle.material.clone_weak());
+        current_batch = Some((particle.key, particle.material.clone_weak(), particle.axis));
+
+        batch_copy(&particle.positions, &mut particle_meta.positions);
+        batch_copy(&particle.sizes, &mut particle_meta.sizes);
+        batch_copy(&particle.colors, &mut particle_meta.colors);
+        batch_copy(&particle.frames, &mut particle_meta.frames);
+        if let Some(velocities) = &particle.velocities {
+            batch_copy(velocities, &mut particle_meta.velocities);
+            velocity_end += velocities.len() as u32;
+        }
+        end += particle.positions.len() as u32;
     }
 
     if start != end {
-        if let Some(current_batch_handle) = &current_batch_handle {
+        if let Some((key, handle, axis)) = &current_batch {
+            let alignment_offset = particle_meta.alignments.push(ParticleAlignmentUniform {
+                axis: *axis,
+                first_particle: start,
+                first_velocity,
+            });
             commands.spawn_bundle((ParticleBatch {
                 range: start..end,
-                handle: current_batch_handle.clone_weak(),
+                handle: handle.clone_weak(),
+                key: *key,
+                alignment_offset,
             },));
         }
     }
@@ -445,6 +722,15 @@ fn prepare_particles(
     particle_meta
         .colors
         .write_buffer(&render_device, &render_queue);
+    particle_meta
+        .frames
+        .write_buffer(&render_device, &render_queue);
+    particle_meta
+        .velocities
+        .write_buffer(&render_device, &render_queue);
+    particle_meta
+        .alignments
+        .write_buffer(&render_device, &render_queue);
 }
 
 fn batch_copy<T: Pod>(src: &Vec<T>, dst: &mut BufferVec<T>) {
@@ -484,6 +770,8 @@ fn image_handle_to_view_sampler<'a>(
 struct ParticleBatch {
     range: Range<u32>,
     handle: Handle<ParticleMaterial>,
+    key: ParticlePipelineKey,
+    alignment_offset: u32,
 }
 
 #[derive(Default)]
@@ -491,6 +779,53 @@ struct MaterialBindGroups {
     values: HashMap<Handle<ParticleMaterial>, BindGroup>,
 }
 
+impl MaterialBindGroups {
+    /// Creates the bind group for a material if it does not already exist.
+    fn queue(
+        &mut self,
+        handle: &Handle<ParticleMaterial>,
+        render_device: &RenderDevice,
+        particle_pipeline: &ParticlePipeline,
+        render_materials: &RenderAssets<ParticleMaterial>,
+        gpu_images: &RenderAssets<Image>,
+    ) {
+        if self.values.contains_key(handle) {
+            return;
+        }
+
+        let gpu_material = render_materials
+            .get(handle)
+            .expect("Failed to get ParticleMaterial PreparedAsset");
+        let (base_color_texture_view, base_color_sampler) = image_handle_to_view_sampler(
+            particle_pipeline,
+            gpu_images,
+            &gpu_material.base_color_texture,
+        );
+
+        self.values.insert(
+            handle.clone_weak(),
+            render_device.create_bind_group(&BindGroupDescriptor {
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: gpu_material.buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(base_color_texture_view),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::Sampler(base_color_sampler),
+                    },
+                ],
+                label: Some("particle_material_bind_group".into()),
+                layout: &particle_pipeline.material_layout,
+            }),
+        );
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 fn queue_particles(
     draw_functions: Res<DrawFunctions<Transparent3d>>,
@@ -523,6 +858,12 @@ fn queue_particles(
         });
     }
 
+    let velocities = if particle_meta.velocity_count > 0 {
+        bind_buffer(&particle_meta.velocities, particle_meta.velocity_count)
+    } else {
+        particle_pipeline.dummy_velocities.as_entire_binding()
+    };
+
     // TODO(james7132): Find a way to cache this.
     particle_meta.particle_bind_group =
         Some(render_device.create_bind_group(&BindGroupDescriptor {
@@ -539,6 +880,18 @@ fn queue_particles(
                     binding: 2,
                     resource: bind_buffer(&particle_meta.colors, particle_meta.total_count),
                 },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: bind_buffer(&particle_meta.frames, particle_meta.total_count),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: velocities,
+                },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: particle_meta.alignments.binding().unwrap(),
+                },
             ],
             label: Some("particle_particle_bind_group".into()),
             layout: &particle_pipeline.particle_layout,
@@ -548,48 +901,18 @@ fn queue_particles(
     let draw_particle_function = draw_functions.read().get_id::<DrawParticle>().unwrap();
     for mut transparent_phase in views.iter_mut() {
         for (entity, batch) in particle_batches.iter() {
-            let gpu_material = render_materials
-                .get(&batch.handle)
-                .expect("Failed to get ParticleMaterial PreparedAsset");
-
-            if !material_bind_groups.values.contains_key(&batch.handle) {
-                let (base_color_texture_view, base_color_sampler) = image_handle_to_view_sampler(
-                    &particle_pipeline,
-                    &gpu_images,
-                    &gpu_material.base_color_texture,
-                );
-
-                material_bind_groups.values.insert(
-                    batch.handle.clone_weak(),
-                    render_device.create_bind_group(&BindGroupDescriptor {
-                        entries: &[
-                            BindGroupEntry {
-                                binding: 0,
-                                resource: gpu_material.buffer.as_entire_binding(),
-                            },
-                            BindGroupEntry {
-                                binding: 1,
-                                resource: BindingResource::TextureView(&base_color_texture_view),
-                            },
-                            BindGroupEntry {
-                                binding: 2,
-                                resource: BindingResource::Sampler(&base_color_sampler),
-                            },
-                        ],
-                        label: Some("particle_material_bind_group".into()),
-                        layout: &particle_pipeline.material_layout,
-                    }),
-                );
-            }
+            material_bind_groups.queue(
+                &batch.handle,
+                &render_device,
+                &particle_pipeline,
+                &render_materials,
+                &gpu_images,
+            );
 
             transparent_phase.add(Transparent3d {
                 // TODO(james7132): properly compute this
                 distance: 10.0,
-                pipeline: pipelines.specialize(
-                    &mut pipeline_cache,
-                    &particle_pipeline,
-                    ParticlePipelineKey,
-                ),
+                pipeline: pipelines.specialize(&mut pipeline_cache, &particle_pipeline, batch.key),
                 entity,
                 draw_function: draw_particle_function,
             });
@@ -639,7 +962,335 @@ impl Draw<Transparent3d> for DrawParticle {
                 particle_meta.view_bind_group.as_ref().unwrap(),
                 &[view_uniform.offset],
             );
-            pass.set_bind_group(1, particle_meta.particle_bind_group.as_ref().unwrap(), &[]);
+            pass.set_bind_group(
+                1,
+                particle_meta.particle_bind_group.as_ref().unwrap(),
+                &[batch.alignment_offset],
+            );
+            pass.set_bind_group(
+                2,
+                material_bind_groups.values.get(&batch.handle).unwrap(),
+                &[],
+            );
+            pass.draw(vertex_range, 0..1);
+        }
+    }
+}
+
+struct ExtractedRibbon {
+    material: Handle<ParticleMaterial>,
+    geometry: RibbonGeometry,
+}
+
+#[derive(Default)]
+struct ExtractedRibbons {
+    ribbons: Vec<ExtractedRibbon>,
+}
+
+fn extract_ribbons(
+    mut render_world: ResMut<RenderWorld>,
+    materials: Res<Assets<ParticleMaterial>>,
+    images: Res<Assets<Image>>,
+    query: Query<(
+        &ComputedVisibility,
+        &Particles,
+        &ParticleRibbon,
+        &Handle<ParticleMaterial>,
+    )>,
+    beams: Query<(
+        &ComputedVisibility,
+        &ParticleBeam,
+        &Handle<ParticleMaterial>,
+    )>,
+) {
+    let mut extracted_ribbons = render_world.get_resource_mut::<ExtractedRibbons>().unwrap();
+    extracted_ribbons.ribbons.clear();
+    for (visible, particles, ribbon, material_handle) in query.iter() {
+        if !visible.is_visible {
+            continue;
+        }
+        if let Some(material) = materials.get(material_handle) {
+            if let Some(ref image) = material.base_color_texture {
+                if !images.contains(image) {
+                    continue;
+                }
+            }
+
+            let mut geometry = RibbonGeometry::default();
+            ribbon.build(particles, &mut geometry);
+            if geometry.segments.is_empty() {
+                continue;
+            }
+            extracted_ribbons.ribbons.push(ExtractedRibbon {
+                material: material_handle.clone_weak(),
+                geometry,
+            });
+        }
+    }
+    for (visible, beam, material_handle) in beams.iter() {
+        if !visible.is_visible {
+            continue;
+        }
+        if let Some(material) = materials.get(material_handle) {
+            if let Some(ref image) = material.base_color_texture {
+                if !images.contains(image) {
+                    continue;
+                }
+            }
+
+            let mut geometry = RibbonGeometry::default();
+            beam.build(&mut geometry);
+            if geometry.segments.is_empty() {
+                continue;
+            }
+            extracted_ribbons.ribbons.push(ExtractedRibbon {
+                material: material_handle.clone_weak(),
+                geometry,
+            });
+        }
+    }
+}
+
+struct RibbonMeta {
+    point_count: u64,
+    segment_count: u64,
+    view_bind_group: Option<BindGroup>,
+    ribbon_bind_group: Option<BindGroup>,
+
+    positions: BufferVec<Vec4>,
+    axes: BufferVec<Vec4>,
+    colors: BufferVec<Vec4>,
+    segments: BufferVec<u32>,
+}
+
+impl Default for RibbonMeta {
+    fn default() -> Self {
+        RibbonMeta {
+            point_count: 0,
+            segment_count: 0,
+            view_bind_group: None,
+            ribbon_bind_group: None,
+
+            positions: BufferVec::new(BufferUsages::STORAGE),
+            axes: BufferVec::new(BufferUsages::STORAGE),
+            colors: BufferVec::new(BufferUsages::STORAGE),
+            segments: BufferVec::new(BufferUsages::STORAGE),
+        }
+    }
+}
+
+#[derive(Component)]
+struct RibbonBatch {
+    /// The range of segments to draw.
+    range: Range<u32>,
+    handle: Handle<ParticleMaterial>,
+}
+
+fn prepare_ribbons(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut commands: Commands,
+    mut ribbon_meta: ResMut<RibbonMeta>,
+    mut extracted_ribbons: ResMut<ExtractedRibbons>,
+) {
+    ribbon_meta.positions.clear();
+    ribbon_meta.axes.clear();
+    ribbon_meta.colors.clear();
+    ribbon_meta.segments.clear();
+
+    extracted_ribbons
+        .ribbons
+        .sort_by(|a, b| a.material.cmp(&b.material));
+
+    let mut point_count = 0;
+    let mut segment_count = 0;
+    for ribbon in extracted_ribbons.ribbons.iter() {
+        point_count += ribbon.geometry.positions.len();
+        segment_count += ribbon.geometry.segments.len();
+    }
+
+    ribbon_meta.point_count = point_count as u64;
+    ribbon_meta.segment_count = segment_count as u64;
+    if segment_count == 0 {
+        return;
+    }
+
+    ribbon_meta.positions.reserve(point_count, &render_device);
+    ribbon_meta.axes.reserve(point_count, &render_device);
+    ribbon_meta.colors.reserve(point_count, &render_device);
+    ribbon_meta.segments.reserve(segment_count, &render_device);
+
+    let mut start: u32 = 0;
+    let mut end: u32 = 0;
+    let mut point_offset: u32 = 0;
+    let mut current_batch_handle: Option<Handle<ParticleMaterial>> = None;
+    for ribbon in extracted_ribbons.ribbons.iter() {
+        if let Some(current_batch_handle) = &current_batch_handle {
+            if *current_batch_handle != ribbon.material {
+                commands.spawn_bundle((RibbonBatch {
+                    range: start..end,
+                    handle: current_batch_handle.clone_weak(),
+                },));
+                start = end;
+            }
+        }
+        current_batch_handle = Some(ribbon.material.clone_weak());
+
+        let geometry = &ribbon.geometry;
+        batch_copy(&geometry.positions, &mut ribbon_meta.positions);
+        batch_copy(&geometry.axes, &mut ribbon_meta.axes);
+        batch_copy(&geometry.colors, &mut ribbon_meta.colors);
+        for segment in geometry.segments.iter() {
+            ribbon_meta.segments.push(point_offset + segment);
+        }
+        point_offset += geometry.positions.len() as u32;
+        end += geometry.segments.len() as u32;
+    }
+
+    if let Some(current_batch_handle) = &current_batch_handle {
+        commands.spawn_bundle((RibbonBatch {
+            range: start..end,
+            handle: current_batch_handle.clone_weak(),
+        },));
+    }
+
+    ribbon_meta
+        .positions
+        .write_buffer(&render_device, &render_queue);
+    ribbon_meta.axes.write_buffer(&render_device, &render_queue);
+    ribbon_meta
+        .colors
+        .write_buffer(&render_device, &render_queue);
+    ribbon_meta
+        .segments
+        .write_buffer(&render_device, &render_queue);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn queue_ribbons(
+    draw_functions: Res<DrawFunctions<Transparent3d>>,
+    mut views: Query<&mut RenderPhase<Transparent3d>>,
+    render_device: Res<RenderDevice>,
+    mut material_bind_groups: ResMut<MaterialBindGroups>,
+    mut ribbon_meta: ResMut<RibbonMeta>,
+    view_uniforms: Res<ViewUniforms>,
+    particle_pipeline: Res<ParticlePipeline>,
+    ribbon_pipeline: Res<RibbonPipeline>,
+    mut pipelines: ResMut<SpecializedPipelines<RibbonPipeline>>,
+    mut pipeline_cache: ResMut<RenderPipelineCache>,
+    ribbon_batches: Query<(Entity, &RibbonBatch)>,
+    render_materials: Res<RenderAssets<ParticleMaterial>>,
+    gpu_images: Res<RenderAssets<Image>>,
+) {
+    if view_uniforms.uniforms.is_empty() || ribbon_meta.segment_count == 0 {
+        return;
+    }
+
+    if let Some(view_bindings) = view_uniforms.uniforms.binding() {
+        ribbon_meta.view_bind_group.get_or_insert_with(|| {
+            render_device.create_bind_group(&BindGroupDescriptor {
+                entries: &[BindGroupEntry {
+                    binding: 0,
+                    resource: view_bindings,
+                }],
+                label: Some("ribbon_view_bind_group".into()),
+                layout: &ribbon_pipeline.view_layout,
+            })
+        });
+    }
+
+    ribbon_meta.ribbon_bind_group = Some(render_device.create_bind_group(&BindGroupDescriptor {
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: bind_buffer(&ribbon_meta.positions, ribbon_meta.point_count),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: bind_buffer(&ribbon_meta.axes, ribbon_meta.point_count),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: bind_buffer(&ribbon_meta.colors, ribbon_meta.point_count),
+            },
+            BindGroupEntry {
+                binding: 3,
+                resource: bind_buffer(&ribbon_meta.segments, ribbon_meta.segment_count),
+            },
+        ],
+        label: Some("ribbon_ribbon_bind_group".into()),
+        layout: &ribbon_pipeline.ribbon_layout,
+    }));
+
+    let draw_ribbon_function = draw_functions.read().get_id::<DrawRibbon>().unwrap();
+    for mut transparent_phase in views.iter_mut() {
+        for (entity, batch) in ribbon_batches.iter() {
+            material_bind_groups.queue(
+                &batch.handle,
+                &render_device,
+                &particle_pipeline,
+                &render_materials,
+                &gpu_images,
+            );
+
+            transparent_phase.add(Transparent3d {
+                // TODO(james7132): properly compute this
+                distance: 10.0,
+                pipeline: pipelines.specialize(
+                    &mut pipeline_cache,
+                    &ribbon_pipeline,
+                    RibbonPipelineKey,
+                ),
+                entity,
+                draw_function: draw_ribbon_function,
+            });
+        }
+    }
+}
+
+struct DrawRibbon {
+    params: SystemState<(
+        SRes<RibbonMeta>,
+        SRes<MaterialBindGroups>,
+        SRes<RenderPipelineCache>,
+        SQuery<Read<ViewUniformOffset>>,
+        SQuery<Read<RibbonBatch>>,
+    )>,
+}
+
+impl DrawRibbon {
+    fn new(world: &mut World) -> Self {
+        Self {
+            params: SystemState::new(world),
+        }
+    }
+}
+
+impl Draw<Transparent3d> for DrawRibbon {
+    fn draw<'w>(
+        &mut self,
+        world: &'w World,
+        pass: &mut TrackedRenderPass<'w>,
+        view: Entity,
+        item: &Transparent3d,
+    ) {
+        let (ribbon_meta, material_bind_groups, pipelines, views, batches) = self.params.get(world);
+        let view_uniform = views.get(view).unwrap();
+        let material_bind_groups = material_bind_groups.into_inner();
+        let ribbon_meta = ribbon_meta.into_inner();
+        let batch = batches.get(item.entity).unwrap();
+
+        if let Some(pipeline) = pipelines.into_inner().get(item.pipeline) {
+            // Every segment is drawn as a quad between two consecutive points.
+            let vertex_range = (batch.range.start * 6)..(batch.range.end * 6);
+
+            pass.set_render_pipeline(pipeline);
+            pass.set_bind_group(
+                0,
+                ribbon_meta.view_bind_group.as_ref().unwrap(),
+                &[view_uniform.offset],
+            );
+            pass.set_bind_group(1, ribbon_meta.ribbon_bind_group.as_ref().unwrap(), &[]);
             pass.set_bind_group(
                 2,
                 material_bind_groups.values.get(&batch.handle).unwrap(),
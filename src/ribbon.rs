@@ -0,0 +1,191 @@
+use crate::{curve, particles::Particles};
+use bevy::{
+    math::{
+        curves::{Curve, CurveFixed},
+        *,
+    },
+    prelude::*,
+};
+use std::cmp::Ordering;
+
+/// Where the points of a ribbon come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RibbonSource {
+    /// Connects every particle in the system into a single ribbon, from the newest particle
+    /// to the oldest.
+    SpawnOrder,
+    /// Draws a ribbon per particle through its recorded positions. Requires the particles to
+    /// be created with [`Particles::with_history`].
+    History,
+}
+
+/// How texture coordinates are laid out along the length of a ribbon.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RibbonUvMode {
+    /// Stretches the texture once over the whole ribbon.
+    Stretch,
+    /// Repeats the texture every given number of world units. Ribbons sample the material's
+    /// texture with the image's own sampler, so the image's `sampler_descriptor` must use
+    /// [`AddressMode::Repeat`](bevy::render::render_resource::AddressMode::Repeat) along U,
+    /// or the last texel is stretched over the rest of the ribbon.
+    Tile(f32),
+}
+
+/// Renders a particle system as camera facing ribbons instead of billboards.
+///
+/// Curves are sampled along the ribbon, from 0.0 at the head to 1.0 at the tail.
+#[derive(Component, Debug, Clone)]
+pub struct ParticleRibbon {
+    pub source: RibbonSource,
+    /// Scales the width of the ribbon, which is otherwise each particle's size.
+    pub width: CurveFixed<f32>,
+    /// Multiplies the color of the ribbon, which is otherwise each particle's color.
+    pub color: CurveFixed<Vec4>,
+    pub uv_mode: RibbonUvMode,
+}
+
+impl ParticleRibbon {
+    pub fn new(source: RibbonSource) -> Self {
+        Self {
+            source,
+            width: curve::from_vec(vec![1.0]),
+            color: curve::from_vec(vec![Vec4::ONE]),
+            uv_mode: RibbonUvMode::Stretch,
+        }
+    }
+
+    pub fn spawn_order() -> Self {
+        Self::new(RibbonSource::SpawnOrder)
+    }
+
+    pub fn history() -> Self {
+        Self::new(RibbonSource::History)
+    }
+
+    pub fn with_width(mut self, width: CurveFixed<f32>) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn with_color(mut self, color: CurveFixed<Vec4>) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_uv_mode(mut self, uv_mode: RibbonUvMode) -> Self {
+        self.uv_mode = uv_mode;
+        self
+    }
+
+    /// Builds the ribbon points for a particle system, appending them to `geometry`.
+    pub(crate) fn build(&self, particles: &Particles, geometry: &mut RibbonGeometry) {
+        let mut points = Vec::new();
+        match self.source {
+            RibbonSource::SpawnOrder => {
+                let mut order: Vec<usize> = (0..particles.len()).collect();
+                order.sort_by(|a, b| {
+                    particles.starts[*b]
+                        .partial_cmp(&particles.starts[*a])
+                        .unwrap_or(Ordering::Equal)
+                });
+                points.extend(order.into_iter().map(|idx| RibbonPoint {
+                    position: particles.positions[idx].xyz(),
+                    size: particles.sizes[idx],
+                    color: particles.colors[idx],
+                }));
+                build_strip(&points, &self.width, &self.color, self.uv_mode, geometry);
+            }
+            RibbonSource::History => {
+                let history = match particles.history.as_ref() {
+                    Some(history) => history,
+                    None => return,
+                };
+                for idx in 0..particles.len() {
+                    let size = particles.sizes[idx];
+                    let color = particles.colors[idx];
+                    points.clear();
+                    points.extend(
+                        std::iter::once(particles.positions[idx].xyz())
+                            .chain(history.iter(idx))
+                            .map(|position| RibbonPoint {
+                                position,
+                                size,
+                                color,
+                            }),
+                    );
+                    build_strip(&points, &self.width, &self.color, self.uv_mode, geometry);
+                }
+            }
+        }
+    }
+}
+
+/// Appends a strip through consecutive points to `geometry`. Curves are sampled along the
+/// strip, from 0.0 at the first point to 1.0 at the last.
+pub(crate) fn build_strip(
+    points: &[RibbonPoint],
+    width: &CurveFixed<f32>,
+    color: &CurveFixed<Vec4>,
+    uv_mode: RibbonUvMode,
+    geometry: &mut RibbonGeometry,
+) {
+    // Coincident points have no direction to orient the ribbon by.
+    let mut strip: Vec<&RibbonPoint> = Vec::with_capacity(points.len());
+    for point in points {
+        match strip.last() {
+            Some(last) if last.position.distance_squared(point.position) <= f32::EPSILON => {}
+            _ => strip.push(point),
+        }
+    }
+    if strip.len() < 2 {
+        return;
+    }
+
+    let mut distances = Vec::with_capacity(strip.len());
+    let mut total = 0.0;
+    for (idx, point) in strip.iter().enumerate() {
+        if idx > 0 {
+            total += strip[idx - 1].position.distance(point.position);
+        }
+        distances.push(total);
+    }
+
+    let start = geometry.positions.len() as u32;
+    let last = strip.len() - 1;
+    for (idx, point) in strip.iter().enumerate() {
+        let t = distances[idx] / total;
+        let u = match uv_mode {
+            RibbonUvMode::Stretch => t,
+            RibbonUvMode::Tile(length) if length > 0.0 => distances[idx] / length,
+            RibbonUvMode::Tile(_) => t,
+        };
+        let prev = strip[idx.saturating_sub(1)].position;
+        let next = strip[(idx + 1).min(last)].position;
+        let tangent = (next - prev).normalize_or_zero();
+        let half_width = point.size * width.sample(t) * 0.5;
+        geometry.positions.push(Vec4::from((point.position, u)));
+        geometry.axes.push(Vec4::from((tangent, half_width)));
+        geometry.colors.push(point.color * color.sample(t));
+    }
+    geometry.segments.extend(start..start + last as u32);
+}
+
+pub(crate) struct RibbonPoint {
+    pub position: Vec3,
+    pub size: f32,
+    pub color: Vec4,
+}
+
+/// The CPU side vertex data for a batch of ribbons.
+#[derive(Default)]
+pub(crate) struct RibbonGeometry {
+    // X, Y, Z - world coordinates
+    // W - U texture coordinate
+    pub positions: Vec<Vec4>,
+    // X, Y, Z - direction along the ribbon
+    // W - half width
+    pub axes: Vec<Vec4>,
+    pub colors: Vec<Vec4>,
+    // The index of the first point of every segment.
+    pub segments: Vec<u32>,
+}
@@ -0,0 +1,96 @@
+// Mirrors the layout of Bevy's `ViewUniform`.
+struct View {
+    view_proj: mat4x4<f32>;
+    inverse_view: mat4x4<f32>;
+    projection: mat4x4<f32>;
+    world_position: vec3<f32>;
+    near: f32;
+    far: f32;
+    width: f32;
+    height: f32;
+};
+[[group(0), binding(0)]]
+var<uniform> view: View;
+
+struct PositionBuffer { data: array<vec4<f32>>; };
+struct AxisBuffer { data: array<vec4<f32>>; };
+struct ColorBuffer { data: array<vec4<f32>>; };
+struct SegmentBuffer { data: array<u32>; };
+
+struct ParticleMaterial {
+  flags: u32;
+  rows: u32;
+  columns: u32;
+};
+
+let FLAGS_BASE_COLOR_TEXTURE_BIT: u32         = 1u;
+
+[[group(1), binding(0)]]
+var<storage, read> positions: PositionBuffer;
+[[group(1), binding(1)]]
+var<storage, read> axes: AxisBuffer;
+[[group(1), binding(2)]]
+var<storage, read> colors: ColorBuffer;
+[[group(1), binding(3)]]
+var<storage, read> segments: SegmentBuffer;
+[[group(2), binding(0)]]
+var<uniform> material: ParticleMaterial;
+[[group(2), binding(1)]]
+var base_color_texture: texture_2d<f32>;
+[[group(2), binding(2)]]
+var base_color_sampler: sampler;
+
+struct VertexInput {
+  [[builtin(vertex_index)]] vertex_idx: u32;
+};
+
+struct VertexOutput {
+  [[builtin(position)]] position: vec4<f32>;
+  [[location(0)]] color: vec4<f32>;
+  [[location(1)]] uv: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(model: VertexInput) -> VertexOutput {
+  // X - which end of the segment, Y - which side of the ribbon.
+  var corners: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
+    vec2<f32>(0.0, -1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(0.0, 1.0),
+    vec2<f32>(0.0, -1.0),
+    vec2<f32>(1.0, -1.0),
+    vec2<f32>(1.0, 1.0),
+  );
+
+  let corner = corners[model.vertex_idx % 6u];
+  let segment_idx = model.vertex_idx / 6u;
+  let point_idx = segments.data[segment_idx] + u32(corner.x);
+
+  let point = positions.data[point_idx];
+  let axis = axes.data[point_idx];
+
+  // Expands the ribbon perpendicular to both its direction and the direction to the camera.
+  let to_camera = view.world_position - point.xyz;
+  var side: vec3<f32> = cross(axis.xyz, to_camera);
+  let side_length = length(side);
+  if (side_length > 0.0) {
+    side = side / side_length;
+  }
+
+  let world_space = point.xyz + side * corner.y * axis.w;
+
+  var out: VertexOutput;
+  out.position = view.view_proj * vec4<f32>(world_space, 1.0);
+  out.color = colors.data[point_idx];
+  out.uv = vec2<f32>(point.w, corner.y * 0.5 + 0.5);
+  return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  var output_color: vec4<f32> = in.color;
+  if ((material.flags & FLAGS_BASE_COLOR_TEXTURE_BIT) != 0u) {
+      output_color = output_color * textureSample(base_color_texture, base_color_sampler, in.uv);
+  }
+  return output_color;
+}
@@ -0,0 +1,390 @@
+use bevy::{
+    math::*,
+    prelude::Color,
+    render::{
+        mesh::{Indices, Mesh, PrimitiveTopology, VertexAttributeValues},
+        render_resource::TextureFormat,
+        texture::Image,
+    },
+};
+use rand::Rng;
+use std::{collections::HashSet, fmt};
+
+/// The name of the optional per-vertex color attribute read by [`MeshSampler`].
+pub const MESH_ATTRIBUTE_COLOR: &str = "Vertex_Color";
+
+#[derive(Debug, Clone)]
+pub enum MeshSamplerError {
+    /// Only triangle lists can be emitted from.
+    UnsupportedTopology(PrimitiveTopology),
+    /// The mesh does not have a `Float32x3` position attribute.
+    MissingPositions,
+    /// The mesh does not have any triangles.
+    Empty,
+    /// An index refers to a vertex past the end of the position attribute.
+    IndexOutOfBounds { index: u32, vertex_count: usize },
+}
+
+impl fmt::Display for MeshSamplerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedTopology(topology) => write!(
+                f,
+                "mesh emitters only support triangle lists, found {:?}",
+                topology
+            ),
+            Self::MissingPositions => {
+                write!(f, "mesh emitters require a Float32x3 position attribute")
+            }
+            Self::Empty => write!(f, "mesh emitters require at least one triangle"),
+            Self::IndexOutOfBounds {
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "mesh index {} is out of bounds for {} vertices",
+                index, vertex_count
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MeshSamplerError {}
+
+/// A point sampled from a mesh, in the mesh's local space.
+#[derive(Debug, Clone, Copy)]
+pub struct MeshSample {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub color: Option<Vec4>,
+    pub uv: Option<Vec2>,
+}
+
+/// Precomputed lookup tables for sampling points on a mesh.
+///
+/// Triangles and edges are chosen by binary searching cumulative area and length tables,
+/// so sampling is `O(log n)` in the size of the mesh.
+#[derive(Debug, Clone)]
+pub struct MeshSampler {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    colors: Option<Vec<Vec4>>,
+    uvs: Option<Vec<Vec2>>,
+    triangles: Vec<[u32; 3]>,
+    triangle_areas: Vec<f32>,
+    edges: Vec<[u32; 2]>,
+    edge_lengths: Vec<f32>,
+}
+
+impl MeshSampler {
+    pub fn from_mesh(mesh: &Mesh) -> Result<Self, MeshSamplerError> {
+        let topology = mesh.primitive_topology();
+        if topology != PrimitiveTopology::TriangleList {
+            return Err(MeshSamplerError::UnsupportedTopology(topology));
+        }
+        let positions: Vec<Vec3> = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float32x3(positions)) => {
+                positions.iter().copied().map(Vec3::from).collect()
+            }
+            _ => return Err(MeshSamplerError::MissingPositions),
+        };
+        let indices: Vec<u32> = match mesh.indices() {
+            Some(Indices::U16(indices)) => indices.iter().map(|idx| *idx as u32).collect(),
+            Some(Indices::U32(indices)) => indices.clone(),
+            None => (0..positions.len() as u32).collect(),
+        };
+        if let Some(index) = indices
+            .iter()
+            .copied()
+            .find(|idx| *idx as usize >= positions.len())
+        {
+            return Err(MeshSamplerError::IndexOutOfBounds {
+                index,
+                vertex_count: positions.len(),
+            });
+        }
+        let triangles: Vec<[u32; 3]> = indices
+            .chunks_exact(3)
+            .map(|tri| [tri[0], tri[1], tri[2]])
+            .collect();
+        if triangles.is_empty() {
+            return Err(MeshSamplerError::Empty);
+        }
+
+        let mut total = 0.0;
+        let triangle_areas = triangles
+            .iter()
+            .map(|tri| {
+                let [a, b, c] = tri.map(|idx| positions[idx as usize]);
+                total += (b - a).cross(c - a).length() * 0.5;
+                total
+            })
+            .collect();
+
+        let mut seen = HashSet::new();
+        let mut edges = Vec::new();
+        for [a, b, c] in triangles.iter().copied() {
+            for edge in [[a, b], [b, c], [c, a]] {
+                if seen.insert((edge[0].min(edge[1]), edge[0].max(edge[1]))) {
+                    edges.push(edge);
+                }
+            }
+        }
+        let mut total = 0.0;
+        let edge_lengths = edges
+            .iter()
+            .map(|edge| {
+                total += positions[edge[0] as usize].distance(positions[edge[1] as usize]);
+                total
+            })
+            .collect();
+
+        let normals = match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+            Some(VertexAttributeValues::Float32x3(normals)) => {
+                normals.iter().copied().map(Vec3::from).collect()
+            }
+            _ => vertex_normals(&positions, &triangles),
+        };
+        let colors = match mesh.attribute(MESH_ATTRIBUTE_COLOR) {
+            Some(VertexAttributeValues::Float32x4(colors)) => {
+                Some(colors.iter().copied().map(Vec4::from).collect())
+            }
+            Some(VertexAttributeValues::Float32x3(colors)) => Some(
+                colors
+                    .iter()
+                    .map(|color| Vec3::from(*color).extend(1.0))
+                    .collect(),
+            ),
+            _ => None,
+        };
+        let uvs = match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            Some(VertexAttributeValues::Float32x2(uvs)) => {
+                Some(uvs.iter().copied().map(Vec2::from).collect())
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            positions,
+            normals,
+            colors,
+            uvs,
+            triangles,
+            triangle_areas,
+            edges,
+            edge_lengths,
+        })
+    }
+
+    /// Samples a point uniformly over the surface of the mesh.
+    pub fn sample_triangle(&self, rng: &mut impl Rng) -> MeshSample {
+        let [a, b, c] = self.triangles[pick_weighted(&self.triangle_areas, rng)];
+        let (mut u, mut v): (f32, f32) = (rng.gen(), rng.gen());
+        if u + v > 1.0 {
+            u = 1.0 - u;
+            v = 1.0 - v;
+        }
+        self.interpolate(&[(a, 1.0 - u - v), (b, u), (c, v)])
+    }
+
+    /// Samples one of the mesh's vertices, with every vertex equally likely.
+    pub fn sample_vertex(&self, rng: &mut impl Rng) -> MeshSample {
+        let idx = rng.gen_range(0..self.positions.len()) as u32;
+        self.interpolate(&[(idx, 1.0)])
+    }
+
+    /// Samples a point uniformly along the edges of the mesh's triangles.
+    pub fn sample_edge(&self, rng: &mut impl Rng) -> MeshSample {
+        let [a, b] = self.edges[pick_weighted(&self.edge_lengths, rng)];
+        let t: f32 = rng.gen();
+        self.interpolate(&[(a, 1.0 - t), (b, t)])
+    }
+
+    fn interpolate(&self, weights: &[(u32, f32)]) -> MeshSample {
+        let mut sample = MeshSample {
+            position: Vec3::ZERO,
+            normal: Vec3::ZERO,
+            color: self.colors.as_ref().map(|_| Vec4::ZERO),
+            uv: self.uvs.as_ref().map(|_| Vec2::ZERO),
+        };
+        for (idx, weight) in weights.iter().copied() {
+            let idx = idx as usize;
+            sample.position += self.positions[idx] * weight;
+            sample.normal += self.normals.get(idx).copied().unwrap_or(Vec3::ZERO) * weight;
+            if let (Some(color), Some(colors)) = (sample.color.as_mut(), &self.colors) {
+                *color += colors.get(idx).copied().unwrap_or(Vec4::ONE) * weight;
+            }
+            if let (Some(uv), Some(uvs)) = (sample.uv.as_mut(), &self.uvs) {
+                *uv += uvs.get(idx).copied().unwrap_or(Vec2::ZERO) * weight;
+            }
+        }
+        sample.normal = sample.normal.normalize_or_zero();
+        sample
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ImageSamplerError {
+    /// Only `Rgba8Unorm` and `Rgba8UnormSrgb` images can be emitted from.
+    UnsupportedFormat(TextureFormat),
+    /// Every texel in the image has zero weight.
+    Empty,
+}
+
+impl fmt::Display for ImageSamplerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedFormat(format) => write!(
+                f,
+                "image emitters only support Rgba8Unorm and Rgba8UnormSrgb images, found {:?}",
+                format
+            ),
+            Self::Empty => write!(f, "image emitters require at least one non-zero texel"),
+        }
+    }
+}
+
+impl std::error::Error for ImageSamplerError {}
+
+/// How texels of an image are weighted when emitting from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageWeight {
+    Alpha,
+    Luminance,
+}
+
+/// A point sampled from an image, in normalized image coordinates.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageSample {
+    /// The sampled point, with `(0, 0)` at the top left of the image and `(1, 1)` at the
+    /// bottom right.
+    pub uv: Vec2,
+    pub color: Color,
+}
+
+/// A precomputed lookup table for sampling texels of an image, weighted by alpha or
+/// luminance.
+#[derive(Debug, Clone)]
+pub struct ImageSampler {
+    width: usize,
+    height: usize,
+    texels: Vec<u32>,
+    weights: Vec<f32>,
+    colors: Vec<Color>,
+}
+
+impl ImageSampler {
+    pub fn from_image(image: &Image, weight: ImageWeight) -> Result<Self, ImageSamplerError> {
+        let format = image.texture_descriptor.format;
+        let srgb = match format {
+            TextureFormat::Rgba8Unorm => false,
+            TextureFormat::Rgba8UnormSrgb => true,
+            _ => return Err(ImageSamplerError::UnsupportedFormat(format)),
+        };
+        let width = image.texture_descriptor.size.width as usize;
+        let height = image.texture_descriptor.size.height as usize;
+
+        let mut texels = Vec::new();
+        let mut weights = Vec::new();
+        let mut colors = Vec::new();
+        let mut total = 0.0;
+        for (idx, texel) in image.data.chunks_exact(4).take(width * height).enumerate() {
+            let [r, g, b, a] = [texel[0], texel[1], texel[2], texel[3]].map(|x| x as f32 / 255.0);
+            let color = if srgb {
+                Color::rgba(r, g, b, a)
+            } else {
+                Color::rgba_linear(r, g, b, a)
+            };
+            let texel_weight = match weight {
+                ImageWeight::Alpha => a,
+                ImageWeight::Luminance => {
+                    let [r, g, b, _] = color.as_linear_rgba_f32();
+                    0.2126 * r + 0.7152 * g + 0.0722 * b
+                }
+            };
+            if texel_weight <= 0.0 {
+                continue;
+            }
+            total += texel_weight;
+            texels.push(idx as u32);
+            weights.push(total);
+            colors.push(color);
+        }
+        if texels.is_empty() {
+            return Err(ImageSamplerError::Empty);
+        }
+
+        Ok(Self {
+            width,
+            height,
+            texels,
+            weights,
+            colors,
+        })
+    }
+
+    /// Samples a random point within a texel, choosing texels by weight.
+    pub fn sample(&self, rng: &mut impl Rng) -> ImageSample {
+        let pick = pick_weighted(&self.weights, rng);
+        let texel = self.texels[pick] as usize;
+        let x = (texel % self.width) as f32 + rng.gen::<f32>();
+        let y = (texel / self.width) as f32 + rng.gen::<f32>();
+        ImageSample {
+            uv: Vec2::new(x / self.width as f32, y / self.height as f32),
+            color: self.colors[pick],
+        }
+    }
+}
+
+/// Picks an index from a cumulative weight table.
+fn pick_weighted(cumulative: &[f32], rng: &mut impl Rng) -> usize {
+    let total = cumulative.last().copied().unwrap_or(0.0);
+    if total <= 0.0 {
+        return rng.gen_range(0..cumulative.len());
+    }
+    let target = rng.gen_range(0.0..total);
+    cumulative
+        .partition_point(|weight| *weight <= target)
+        .min(cumulative.len() - 1)
+}
+
+/// Computes area weighted vertex normals for meshes that do not provide their own.
+fn vertex_normals(positions: &[Vec3], triangles: &[[u32; 3]]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::ZERO; positions.len()];
+    for tri in triangles {
+        let [a, b, c] = tri.map(|idx| positions[idx as usize]);
+        let normal = (b - a).cross(c - a);
+        for idx in tri {
+            normals[*idx as usize] += normal;
+        }
+    }
+    normals
+        .into_iter()
+        .map(|normal| normal.normalize_or_zero())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_out_of_bounds_indices() {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.set_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+        );
+        mesh.set_indices(Some(Indices::U32(vec![0, 1, 2, 0, 2, 3])));
+        assert!(matches!(
+            MeshSampler::from_mesh(&mesh),
+            Err(MeshSamplerError::IndexOutOfBounds {
+                index: 3,
+                vertex_count: 3,
+            })
+        ));
+
+        mesh.set_indices(Some(Indices::U32(vec![0, 1, 2])));
+        assert!(MeshSampler::from_mesh(&mesh).is_ok());
+    }
+}
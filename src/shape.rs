@@ -0,0 +1,893 @@
+use crate::{
+    emitter::ParticleEmitter,
+    sampler::{ImageSampler, ImageWeight, MeshSampler},
+};
+use bevy::{
+    asset::{AssetEvent, Assets, Handle},
+    log::warn,
+    math::*,
+    prelude::*,
+    render::{mesh::Mesh, texture::Image},
+};
+use rand::Rng;
+use std::{
+    collections::{HashMap, HashSet},
+    f32::consts::PI,
+    ops::Range,
+    sync::Arc,
+};
+
+const TWO_PI: f32 = PI * 2.0;
+
+/// Where within a shape particles are emitted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmissionMode {
+    /// Emit from anywhere within the shape, limited by the emission's radius thickness.
+    Volume,
+    /// Emit only from the surface or edge of the shape.
+    Shell,
+}
+
+/// How points emitted in [`EmissionMode::Volume`] are spread between the center of a shape
+/// and its surface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RadiusDistribution {
+    /// Picks the distance from the center uniformly, which concentrates particles toward
+    /// the center of the shape. This is how sphere emitters have always behaved.
+    Linear,
+    /// Spreads points uniformly over the area or volume of the shape.
+    Uniform,
+}
+
+/// The direction particles travel in when emitted from an [`EmitterShape`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmissionDirection {
+    /// Away from the shape's center or axis. Cones emit along the cone, and meshes and images
+    /// emit along their normal.
+    FromCenter,
+    /// Along the surface normal at the emitted point. Planar shapes emit along +Y.
+    Normal,
+    /// Along a fixed direction in the emitter's local space. A zero axis emits away from the
+    /// shape's center, as with [`EmissionDirection::FromCenter`].
+    Axis(Vec3),
+    /// In a uniformly random direction.
+    Random,
+    /// Toward a point in the emitter's local space.
+    Target(Vec3),
+}
+
+/// Controls how particles are distributed within an [`EmitterShape`], and the direction
+/// they are emitted in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShapeEmission {
+    pub mode: EmissionMode,
+    /// The fraction of the shape's radius, measured inward from its surface, that particles
+    /// are emitted from in [`EmissionMode::Volume`]. 1.0 emits from the entire volume, and
+    /// values near 0.0 emit from a thin layer just under the surface.
+    pub radius_thickness: f32,
+    pub radius_distribution: RadiusDistribution,
+    pub direction: EmissionDirection,
+    /// The maximum angle, in radians, that directions are randomly spread from the
+    /// direction given by `direction`. PI randomizes the direction completely.
+    pub spread: f32,
+    /// If true, each particle's start rotation is aligned to its direction of travel, as seen
+    /// by a camera looking down the Z axis. The rotation is fixed at spawn, so use
+    /// [`BillboardAlignment::Velocity`](crate::BillboardAlignment::Velocity) to align
+    /// particles to their velocity from any view.
+    pub align_rotation: bool,
+}
+
+impl Default for ShapeEmission {
+    fn default() -> Self {
+        Self {
+            mode: EmissionMode::Volume,
+            radius_thickness: 1.0,
+            radius_distribution: RadiusDistribution::Linear,
+            direction: EmissionDirection::FromCenter,
+            spread: 0.0,
+            align_rotation: false,
+        }
+    }
+}
+
+impl ShapeEmission {
+    /// Samples a normalized radius within the outer shell given by the radius thickness.
+    /// With [`RadiusDistribution::Uniform`], points are uniformly distributed over a
+    /// `dimensions`-dimensional ball.
+    fn sample_radius(&self, dimensions: i32, rng: &mut impl Rng) -> f32 {
+        let thickness = self.radius_thickness.clamp(0.0, 1.0);
+        let u: f32 = rng.gen_range(0.0..=1.0);
+        match (self.mode, self.radius_distribution) {
+            (EmissionMode::Shell, _) => 1.0,
+            (EmissionMode::Volume, RadiusDistribution::Linear) => 1.0 - thickness * u,
+            (EmissionMode::Volume, RadiusDistribution::Uniform) => {
+                let inner = (1.0 - thickness).powi(dimensions);
+                (inner + (1.0 - inner) * u).powf(1.0 / dimensions as f32)
+            }
+        }
+    }
+
+    /// Picks the unit direction a particle emitted at `sample` travels in.
+    pub fn sample_direction(&self, sample: &ShapeSample, rng: &mut impl Rng) -> Vec3 {
+        let direction = match self.direction {
+            EmissionDirection::FromCenter => sample.outward,
+            EmissionDirection::Normal => sample.normal,
+            EmissionDirection::Axis(axis) => match axis.normalize_or_zero() {
+                Vec3::ZERO => sample.outward,
+                axis => axis,
+            },
+            EmissionDirection::Random => sample_sphere(rng),
+            EmissionDirection::Target(target) => (target - sample.position).normalize_or_zero(),
+        };
+        if self.spread > 0.0 && direction != Vec3::ZERO {
+            sample_cone(direction, self.spread, rng)
+        } else {
+            direction
+        }
+    }
+}
+
+/// A point sampled from an [`EmitterShape`], in the emitter's local space.
+#[derive(Debug, Clone, Copy)]
+pub struct ShapeSample {
+    pub position: Vec3,
+    /// The surface normal at the point.
+    pub normal: Vec3,
+    /// The direction away from the shape's center or axis.
+    pub outward: Vec3,
+    /// The color sampled from the shape, for shapes that provide one.
+    pub color: Option<Color>,
+}
+
+impl ShapeSample {
+    fn new(position: Vec3, normal: Vec3, outward: Vec3) -> Self {
+        Self {
+            position,
+            normal,
+            outward,
+            color: None,
+        }
+    }
+}
+
+/// Which part of a mesh particles are emitted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshSampleMode {
+    /// Anywhere on the mesh's surface, weighted by triangle area.
+    Triangles,
+    /// From the mesh's vertices.
+    Vertices,
+    /// Anywhere along the mesh's edges, weighted by edge length.
+    Edges,
+}
+
+/// What a particle emitted from a mesh takes its color from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshColorSource {
+    /// Keep the emitter's color.
+    None,
+    /// The interpolated vertex color, if the mesh has one.
+    VertexColor,
+    /// The interpolated UV coordinates encoded as a color, with U in red, V in green, zero
+    /// blue and full alpha. The mesh's material is not sampled; this is meant for
+    /// [`EmitterModifier`](crate::EmitterModifier)s or materials that look up their own
+    /// texture by UV. Meshes without UVs keep the emitter's color.
+    UvAsColor,
+}
+
+/// The shape that particles are emitted from, in the emitter's local space.
+///
+/// Planar shapes lie in the XZ plane and face +Y.
+#[derive(Debug, Clone)]
+pub enum EmitterShape {
+    Sphere {
+        center: Vec3,
+        radius: f32,
+    },
+    Hemisphere {
+        center: Vec3,
+        radius: f32,
+    },
+    Box {
+        center: Vec3,
+        half_extents: Vec3,
+    },
+    /// A cone opening along +Y from a base of the given radius. Particles are emitted from
+    /// the base, or anywhere up to `length` along the cone in [`EmissionMode::Volume`].
+    Cone {
+        center: Vec3,
+        /// The angle between the cone's axis and its sides, in radians.
+        angle: f32,
+        radius: f32,
+        length: f32,
+    },
+    /// A circle, or a disc in [`EmissionMode::Volume`], limited to an arc of angles in
+    /// radians.
+    Circle {
+        center: Vec3,
+        radius: f32,
+        arc: Range<f32>,
+    },
+    /// A line segment. The radius thickness is measured inward from both ends toward the
+    /// middle, so [`EmissionMode::Shell`] only emits from the two ends.
+    Line {
+        start: Vec3,
+        end: Vec3,
+    },
+    /// A torus around the Y axis, limited to an arc of angles in radians.
+    Torus {
+        center: Vec3,
+        radius: f32,
+        tube_radius: f32,
+        arc: Range<f32>,
+    },
+    /// The surface, vertices or edges of a mesh. Particles are emitted along the
+    /// interpolated normal. Nothing is emitted until the mesh has loaded.
+    Mesh {
+        mesh: Handle<Mesh>,
+        mode: MeshSampleMode,
+        color: MeshColorSource,
+    },
+    /// A quad of the given size in the XZ plane, with texels of an `Rgba8Unorm` or
+    /// `Rgba8UnormSrgb` image chosen by weight. The top of the image faces -Z and particles
+    /// are emitted along +Y. Nothing is emitted until the image has loaded.
+    Image {
+        image: Handle<Image>,
+        weight: ImageWeight,
+        size: Vec2,
+        /// If true, particles take their color from the sampled texel.
+        use_color: bool,
+    },
+}
+
+impl EmitterShape {
+    /// Whether any assets the shape depends on are loaded and ready to be sampled.
+    pub fn is_ready(&self, cache: &EmitterShapeCache) -> bool {
+        match self {
+            Self::Mesh { mesh, .. } => cache.mesh(mesh).is_some(),
+            Self::Image { image, weight, .. } => cache.image(image, *weight).is_some(),
+            _ => true,
+        }
+    }
+
+    pub fn sample(
+        &self,
+        emission: &ShapeEmission,
+        cache: &EmitterShapeCache,
+        rng: &mut impl Rng,
+    ) -> ShapeSample {
+        match self {
+            Self::Sphere { radius, center } => {
+                let direction = sample_sphere(rng);
+                let r = emission.sample_radius(3, rng);
+                ShapeSample::new(direction * r * *radius + *center, direction, direction)
+            }
+            Self::Hemisphere { radius, center } => {
+                let mut direction = sample_sphere(rng);
+                direction.y = f32::abs(direction.y);
+                let r = emission.sample_radius(3, rng);
+                ShapeSample::new(direction * r * *radius + *center, direction, direction)
+            }
+            Self::Box {
+                center,
+                half_extents,
+            } => {
+                let (surface, normal) = sample_box_surface(*half_extents, emission.mode, rng);
+                let r = emission.sample_radius(3, rng);
+                ShapeSample::new(surface * r + *center, normal, surface.normalize_or_zero())
+            }
+            Self::Cone {
+                center,
+                angle,
+                radius,
+                length,
+            } => {
+                let theta = rng.gen_range(0.0..TWO_PI);
+                let r = emission.sample_radius(2, rng);
+                let radial = Vec3::new(theta.cos(), 0.0, theta.sin());
+                let (sin, cos) = (angle * r).sin_cos();
+                let direction = Vec3::Y * cos + radial * sin;
+                let distance = match emission.mode {
+                    EmissionMode::Volume => rng.gen_range(0.0..=1.0) * *length,
+                    EmissionMode::Shell => 0.0,
+                };
+                let position = radial * r * *radius + direction * distance + *center;
+                ShapeSample::new(position, Vec3::Y, direction)
+            }
+            Self::Circle {
+                center,
+                radius,
+                arc,
+            } => {
+                let theta = sample_arc(arc, rng);
+                let radial = Vec3::new(theta.cos(), 0.0, theta.sin());
+                let r = emission.sample_radius(2, rng);
+                ShapeSample::new(radial * r * *radius + *center, Vec3::Y, radial)
+            }
+            Self::Line { start, end } => {
+                let axis = (*end - *start).normalize_or_zero();
+                let direction = sample_sphere(rng);
+                let side = if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+                let t = 0.5 + 0.5 * side * emission.sample_radius(1, rng);
+                let outward = (direction - axis * direction.dot(axis)).normalize_or_zero();
+                ShapeSample::new(start.lerp(*end, t), outward, outward)
+            }
+            Self::Torus {
+                center,
+                radius,
+                tube_radius,
+                arc,
+            } => {
+                let theta = sample_arc(arc, rng);
+                let phi = rng.gen_range(0.0..TWO_PI);
+                let radial = Vec3::new(theta.cos(), 0.0, theta.sin());
+                let direction = radial * phi.cos() + Vec3::Y * phi.sin();
+                let r = emission.sample_radius(2, rng);
+                let position = radial * *radius + direction * r * *tube_radius + *center;
+                ShapeSample::new(position, direction, direction)
+            }
+            Self::Mesh { mesh, mode, color } => {
+                let sampler = match cache.mesh(mesh) {
+                    Some(sampler) => sampler,
+                    None => return ShapeSample::new(Vec3::ZERO, Vec3::Y, Vec3::Y),
+                };
+                let sample = match mode {
+                    MeshSampleMode::Triangles => sampler.sample_triangle(rng),
+                    MeshSampleMode::Vertices => sampler.sample_vertex(rng),
+                    MeshSampleMode::Edges => sampler.sample_edge(rng),
+                };
+                let mut shape_sample =
+                    ShapeSample::new(sample.position, sample.normal, sample.normal);
+                shape_sample.color = match (color, sample.color, sample.uv) {
+                    (MeshColorSource::VertexColor, Some(color), _) => Some(Color::from(color)),
+                    (MeshColorSource::UvAsColor, _, Some(uv)) => Some(Color::rgb(uv.x, uv.y, 0.0)),
+                    _ => None,
+                };
+                shape_sample
+            }
+            Self::Image {
+                image,
+                weight,
+                size,
+                use_color,
+            } => {
+                let sampler = match cache.image(image, *weight) {
+                    Some(sampler) => sampler,
+                    None => return ShapeSample::new(Vec3::ZERO, Vec3::Y, Vec3::Y),
+                };
+                let sample = sampler.sample(rng);
+                let offset = (sample.uv - Vec2::splat(0.5)) * *size;
+                let position = Vec3::new(offset.x, 0.0, offset.y);
+                let mut shape_sample = ShapeSample::new(position, Vec3::Y, Vec3::Y);
+                if *use_color {
+                    shape_sample.color = Some(sample.color);
+                }
+                shape_sample
+            }
+        }
+    }
+}
+
+/// A cache of the lookup tables built for sampling [`EmitterShape`]s that use assets, keyed
+/// by asset handle.
+///
+/// Entries are rebuilt whenever the underlying asset is modified. Meshes and images that fail
+/// to build are only retried once they are modified.
+#[derive(Default)]
+pub struct EmitterShapeCache {
+    meshes: HashMap<Handle<Mesh>, Arc<MeshSampler>>,
+    failed_meshes: HashSet<Handle<Mesh>>,
+    images: HashMap<(Handle<Image>, ImageWeight), Arc<ImageSampler>>,
+    failed_images: HashSet<(Handle<Image>, ImageWeight)>,
+}
+
+impl EmitterShapeCache {
+    pub fn mesh(&self, mesh: &Handle<Mesh>) -> Option<&Arc<MeshSampler>> {
+        self.meshes.get(mesh)
+    }
+
+    pub fn image(&self, image: &Handle<Image>, weight: ImageWeight) -> Option<&Arc<ImageSampler>> {
+        self.images.get(&(image.clone_weak(), weight))
+    }
+}
+
+pub fn update_emitter_shape_cache(
+    mut events: EventReader<AssetEvent<Mesh>>,
+    mut image_events: EventReader<AssetEvent<Image>>,
+    meshes: Res<Assets<Mesh>>,
+    images: Res<Assets<Image>>,
+    mut cache: ResMut<EmitterShapeCache>,
+    emitters: Query<&ParticleEmitter>,
+) {
+    for event in image_events.iter() {
+        match event {
+            AssetEvent::Modified { handle } | AssetEvent::Removed { handle } => {
+                cache.images.retain(|(image, _), _| image != handle);
+                cache.failed_images.retain(|(image, _)| image != handle);
+            }
+            AssetEvent::Created { .. } => {}
+        }
+    }
+    for event in events.iter() {
+        match event {
+            AssetEvent::Modified { handle } | AssetEvent::Removed { handle } => {
+                cache.meshes.remove(handle);
+                cache.failed_meshes.remove(handle);
+            }
+            AssetEvent::Created { .. } => {}
+        }
+    }
+
+    for emitter in emitters.iter() {
+        match emitter.shape() {
+            EmitterShape::Mesh { mesh: handle, .. } => {
+                if cache.meshes.contains_key(handle) || cache.failed_meshes.contains(handle) {
+                    continue;
+                }
+                if let Some(mesh) = meshes.get(handle) {
+                    match MeshSampler::from_mesh(mesh) {
+                        Ok(sampler) => {
+                            cache.meshes.insert(handle.clone_weak(), Arc::new(sampler));
+                        }
+                        Err(err) => {
+                            warn!("Failed to build mesh emitter: {}", err);
+                            cache.failed_meshes.insert(handle.clone_weak());
+                        }
+                    }
+                }
+            }
+            EmitterShape::Image {
+                image: handle,
+                weight,
+                ..
+            } => {
+                let key = (handle.clone_weak(), *weight);
+                if cache.images.contains_key(&key) || cache.failed_images.contains(&key) {
+                    continue;
+                }
+                if let Some(image) = images.get(handle) {
+                    match ImageSampler::from_image(image, *weight) {
+                        Ok(sampler) => {
+                            cache.images.insert(key, Arc::new(sampler));
+                        }
+                        Err(err) => {
+                            warn!("Failed to build image emitter: {}", err);
+                            cache.failed_images.insert(key);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Select one point at random on the unit sphere.
+pub(crate) fn sample_sphere(rng: &mut impl Rng) -> Vec3 {
+    let theta = rng.gen_range(0.0..TWO_PI);
+    let z = rng.gen_range(-1.0..1.0);
+    let x = f32::sqrt(1.0 - z * z) * f32::cos(theta);
+    let y = f32::sqrt(1.0 - z * z) * f32::sin(theta);
+
+    Vec3::from((x, y, z))
+}
+
+/// Select one direction at random within a cone around a unit `axis`, where `angle` is the
+/// angle between the axis and the edge of the cone, in radians.
+pub(crate) fn sample_cone(axis: Vec3, angle: f32, rng: &mut impl Rng) -> Vec3 {
+    // A zero axis has no direction to spread around, and would rotate `local` to NaN.
+    if axis == Vec3::ZERO {
+        return axis;
+    }
+    let cos_angle = angle.clamp(0.0, PI).cos();
+    let z = rng.gen_range(cos_angle..=1.0);
+    let theta = rng.gen_range(0.0..TWO_PI);
+    let r = f32::sqrt(1.0 - z * z);
+    let local = Vec3::new(r * theta.cos(), r * theta.sin(), z);
+    Quat::from_rotation_arc(Vec3::Z, axis) * local
+}
+
+/// Select one angle at random within an arc, in radians.
+fn sample_arc(arc: &Range<f32>, rng: &mut impl Rng) -> f32 {
+    if arc.end > arc.start {
+        rng.gen_range(arc.clone())
+    } else {
+        arc.start
+    }
+}
+
+/// Select one point at random on the surface of a box centered on the origin.
+///
+/// In [`EmissionMode::Volume`], faces are weighted by the volume of the pyramid between
+/// them and the center, so that scaling the point toward the center by a uniformly sampled
+/// cubic radius yields a uniform distribution over the volume. Otherwise, faces are
+/// weighted by area.
+/// Returns the point and the normal of the face it lies on.
+fn sample_box_surface(half_extents: Vec3, mode: EmissionMode, rng: &mut impl Rng) -> (Vec3, Vec3) {
+    let areas = Vec3::new(
+        half_extents.y * half_extents.z,
+        half_extents.x * half_extents.z,
+        half_extents.x * half_extents.y,
+    );
+    let weights = match mode {
+        EmissionMode::Volume => areas * half_extents,
+        EmissionMode::Shell => areas,
+    };
+    let total = weights.x + weights.y + weights.z;
+    if total <= 0.0 {
+        return (Vec3::ZERO, Vec3::Y);
+    }
+    let pick = rng.gen_range(0.0..total);
+    let axis = if pick < weights.x {
+        0
+    } else if pick < weights.x + weights.y {
+        1
+    } else {
+        2
+    };
+    let mut point = Vec3::new(
+        rng.gen_range(-1.0..=1.0),
+        rng.gen_range(-1.0..=1.0),
+        rng.gen_range(-1.0..=1.0),
+    );
+    let side = if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+    point[axis] = side;
+    let mut normal = Vec3::ZERO;
+    normal[axis] = side;
+    (point * half_extents, normal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    };
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    const SAMPLES: usize = 2000;
+    const EPSILON: f32 = 1e-4;
+
+    fn emissions() -> Vec<ShapeEmission> {
+        let mut emissions = Vec::new();
+        for mode in [EmissionMode::Volume, EmissionMode::Shell] {
+            for radius_thickness in [1.0, 0.25] {
+                for radius_distribution in [RadiusDistribution::Linear, RadiusDistribution::Uniform]
+                {
+                    emissions.push(ShapeEmission {
+                        mode,
+                        radius_thickness,
+                        radius_distribution,
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+        emissions
+    }
+
+    fn samples(
+        shape: &EmitterShape,
+        emission: &ShapeEmission,
+        cache: &EmitterShapeCache,
+    ) -> Vec<ShapeSample> {
+        let mut rng = SmallRng::seed_from_u64(0);
+        (0..SAMPLES)
+            .map(|_| shape.sample(emission, cache, &mut rng))
+            .collect()
+    }
+
+    /// Checks every sample of a shape for every combination of emission settings.
+    fn check(shape: EmitterShape, assert_sample: impl Fn(&ShapeSample, &ShapeEmission)) {
+        let cache = EmitterShapeCache::default();
+        for emission in emissions() {
+            for sample in samples(&shape, &emission, &cache) {
+                assert_sample(&sample, &emission);
+            }
+        }
+    }
+
+    /// Checks that a normalized radius lies within the shell given by the radius thickness.
+    fn assert_radius(radius: f32, emission: &ShapeEmission) {
+        match emission.mode {
+            EmissionMode::Shell => {
+                assert!(
+                    (radius - 1.0).abs() < EPSILON,
+                    "radius {} off the shell",
+                    radius
+                )
+            }
+            EmissionMode::Volume => {
+                let inner = 1.0 - emission.radius_thickness;
+                assert!(
+                    radius >= inner - EPSILON && radius <= 1.0 + EPSILON,
+                    "radius {} outside {}..=1.0",
+                    radius,
+                    inner
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn sphere_bounds() {
+        let center = Vec3::new(1.0, 2.0, 3.0);
+        check(
+            EmitterShape::Sphere {
+                center,
+                radius: 2.0,
+            },
+            |sample, emission| {
+                assert_radius((sample.position - center).length() / 2.0, emission);
+            },
+        );
+    }
+
+    #[test]
+    fn hemisphere_bounds() {
+        let center = Vec3::new(1.0, 2.0, 3.0);
+        check(
+            EmitterShape::Hemisphere {
+                center,
+                radius: 2.0,
+            },
+            |sample, emission| {
+                assert_radius((sample.position - center).length() / 2.0, emission);
+                assert!(sample.position.y >= center.y - EPSILON);
+            },
+        );
+    }
+
+    #[test]
+    fn sphere_radius_distribution() {
+        let shape = EmitterShape::Sphere {
+            center: Vec3::ZERO,
+            radius: 1.0,
+        };
+        let cache = EmitterShapeCache::default();
+        let mean_radius = |radius_distribution| {
+            let emission = ShapeEmission {
+                radius_distribution,
+                ..Default::default()
+            };
+            let samples = samples(&shape, &emission, &cache);
+            samples
+                .iter()
+                .map(|sample| sample.position.length())
+                .sum::<f32>()
+                / samples.len() as f32
+        };
+        assert!((mean_radius(RadiusDistribution::Linear) - 0.5).abs() < 0.05);
+        assert!((mean_radius(RadiusDistribution::Uniform) - 0.75).abs() < 0.05);
+    }
+
+    #[test]
+    fn box_bounds() {
+        let center = Vec3::new(1.0, 2.0, 3.0);
+        let half_extents = Vec3::new(1.0, 2.0, 3.0);
+        check(
+            EmitterShape::Box {
+                center,
+                half_extents,
+            },
+            |sample, emission| {
+                let radius = ((sample.position - center) / half_extents)
+                    .abs()
+                    .max_element();
+                assert_radius(radius, emission);
+            },
+        );
+    }
+
+    #[test]
+    fn cone_bounds() {
+        let (angle, radius, length) = (PI / 6.0, 1.0, 2.0);
+        check(
+            EmitterShape::Cone {
+                center: Vec3::ZERO,
+                angle,
+                radius,
+                length,
+            },
+            |sample, emission| {
+                let position = sample.position;
+                let horizontal = Vec2::new(position.x, position.z).length();
+                match emission.mode {
+                    EmissionMode::Shell => {
+                        assert!(position.y.abs() < EPSILON);
+                        assert_radius(horizontal / radius, emission);
+                    }
+                    EmissionMode::Volume => {
+                        let inner = (1.0 - emission.radius_thickness) * radius;
+                        assert!(position.y >= -EPSILON && position.y <= length + EPSILON);
+                        assert!(horizontal >= inner - EPSILON);
+                        assert!(horizontal <= radius + length * angle.sin() + EPSILON);
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn circle_bounds_and_arc() {
+        check(
+            EmitterShape::Circle {
+                center: Vec3::ZERO,
+                radius: 2.0,
+                arc: 0.0..PI / 2.0,
+            },
+            |sample, emission| {
+                let position = sample.position;
+                assert!(position.y.abs() < EPSILON);
+                assert!(position.x >= -EPSILON && position.z >= -EPSILON);
+                assert_radius(position.length() / 2.0, emission);
+            },
+        );
+    }
+
+    #[test]
+    fn line_bounds() {
+        check(
+            EmitterShape::Line {
+                start: Vec3::new(-1.0, 0.0, 0.0),
+                end: Vec3::new(1.0, 0.0, 0.0),
+            },
+            |sample, emission| {
+                let position = sample.position;
+                assert!(position.y.abs() < EPSILON && position.z.abs() < EPSILON);
+                assert_radius(position.x.abs(), emission);
+            },
+        );
+    }
+
+    #[test]
+    fn torus_bounds_and_arc() {
+        let (radius, tube_radius) = (2.0, 0.5);
+        check(
+            EmitterShape::Torus {
+                center: Vec3::ZERO,
+                radius,
+                tube_radius,
+                arc: 0.0..PI,
+            },
+            |sample, emission| {
+                let position = sample.position;
+                let ring = Vec3::new(position.x, 0.0, position.z).normalize() * radius;
+                assert!(position.z >= -EPSILON);
+                assert_radius((position - ring).length() / tube_radius, emission);
+            },
+        );
+    }
+
+    #[test]
+    fn zero_axis_emits_outward() {
+        let shape = EmitterShape::Sphere {
+            center: Vec3::ZERO,
+            radius: 1.0,
+        };
+        let emission = ShapeEmission {
+            mode: EmissionMode::Shell,
+            direction: EmissionDirection::Axis(Vec3::ZERO),
+            spread: PI / 4.0,
+            ..Default::default()
+        };
+        let cache = EmitterShapeCache::default();
+        let mut rng = SmallRng::seed_from_u64(0);
+        for sample in samples(&shape, &emission, &cache) {
+            let direction = emission.sample_direction(&sample, &mut rng);
+            assert!(direction.is_finite());
+            assert!((direction.length() - 1.0).abs() < EPSILON);
+            assert!(direction.dot(sample.outward) >= (PI / 4.0).cos() - EPSILON);
+        }
+    }
+
+    #[test]
+    fn mesh_bounds() {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.set_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            vec![
+                [-1.0, 0.0, -1.0],
+                [1.0, 0.0, -1.0],
+                [1.0, 0.0, 1.0],
+                [-1.0, 0.0, 1.0],
+            ],
+        );
+        mesh.set_indices(Some(Indices::U32(vec![0, 2, 1, 0, 3, 2])));
+        let handle = Handle::<Mesh>::default();
+        let mut cache = EmitterShapeCache::default();
+        cache.meshes.insert(
+            handle.clone_weak(),
+            Arc::new(MeshSampler::from_mesh(&mesh).unwrap()),
+        );
+
+        for mode in [
+            MeshSampleMode::Triangles,
+            MeshSampleMode::Vertices,
+            MeshSampleMode::Edges,
+        ] {
+            let shape = EmitterShape::Mesh {
+                mesh: handle.clone_weak(),
+                mode,
+                color: MeshColorSource::None,
+            };
+            for sample in samples(&shape, &ShapeEmission::default(), &cache) {
+                let position = sample.position;
+                assert!(position.y.abs() < EPSILON);
+                assert!(position.x.abs() <= 1.0 + EPSILON && position.z.abs() <= 1.0 + EPSILON);
+                assert!(sample.outward.distance(Vec3::Y) < EPSILON);
+                if mode == MeshSampleMode::Vertices {
+                    assert!((position.x.abs() - 1.0).abs() < EPSILON);
+                    assert!((position.z.abs() - 1.0).abs() < EPSILON);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mesh_uv_as_color() {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.set_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+        );
+        mesh.set_attribute(
+            Mesh::ATTRIBUTE_UV_0,
+            vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]],
+        );
+        let handle = Handle::<Mesh>::default();
+        let mut cache = EmitterShapeCache::default();
+        cache.meshes.insert(
+            handle.clone_weak(),
+            Arc::new(MeshSampler::from_mesh(&mesh).unwrap()),
+        );
+
+        let shape = EmitterShape::Mesh {
+            mesh: handle.clone_weak(),
+            mode: MeshSampleMode::Triangles,
+            color: MeshColorSource::UvAsColor,
+        };
+        for sample in samples(&shape, &ShapeEmission::default(), &cache) {
+            // The UVs match the position on the XZ plane.
+            let [r, g, b, a] = sample.color.unwrap().as_rgba_f32();
+            assert!((r - sample.position.x).abs() < EPSILON);
+            assert!((g - sample.position.z).abs() < EPSILON);
+            assert_eq!((b, a), (0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn image_bounds() {
+        let image = Image::new_fill(
+            Extent3d {
+                width: 2,
+                height: 2,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[255, 255, 255, 255],
+            TextureFormat::Rgba8Unorm,
+        );
+        let handle = Handle::<Image>::default();
+        let mut cache = EmitterShapeCache::default();
+        cache.images.insert(
+            (handle.clone_weak(), ImageWeight::Alpha),
+            Arc::new(ImageSampler::from_image(&image, ImageWeight::Alpha).unwrap()),
+        );
+
+        let shape = EmitterShape::Image {
+            image: handle,
+            weight: ImageWeight::Alpha,
+            size: Vec2::new(2.0, 4.0),
+            use_color: true,
+        };
+        for sample in samples(&shape, &ShapeEmission::default(), &cache) {
+            let position = sample.position;
+            assert!(position.y.abs() < EPSILON);
+            assert!(position.x.abs() <= 1.0 + EPSILON && position.z.abs() <= 2.0 + EPSILON);
+            assert_eq!(sample.outward, Vec3::Y);
+            assert!(sample.color.is_some());
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use crate::particles::{ParticleLayers, ParticleParams, Particles};
+use bevy::{math::*, prelude::*, tasks::ComputeTaskPool};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VolumeShape {
+    /// A box centered on the origin. Rotating the entity turns it into an oriented box.
+    Box { half_extents: Vec3 },
+    /// A sphere centered on the origin.
+    Sphere { radius: f32 },
+}
+
+impl VolumeShape {
+    #[inline]
+    fn contains(&self, local: Vec3) -> bool {
+        match self {
+            Self::Box { half_extents } => local.abs().cmple(*half_extents).all(),
+            Self::Sphere { radius } => local.length_squared() <= radius * radius,
+        }
+    }
+}
+
+/// When a [`ParticleVolume`] acts on a particle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeTrigger {
+    /// Every update that the particle is inside of the volume.
+    Inside,
+    /// The update that the particle moves into the volume.
+    Enter,
+    /// The update that the particle moves out of the volume.
+    Exit,
+}
+
+/// What a [`ParticleVolume`] does to a particle when triggered.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VolumeAction {
+    /// Kills the particle.
+    Kill,
+    /// Sets the particle's color.
+    Recolor(Color),
+    /// Sends a [`ParticleVolumeEvent`].
+    Event,
+    /// Moves the particle into another entity's [`Particles`], keeping its age and remaining
+    /// lifetime. Its color, size and frame are reset to the ones it was spawned with, to be
+    /// modified by the other system.
+    Transfer(Entity),
+}
+
+/// A volume that acts on particles that are inside, enter, or leave it.
+///
+/// Volumes are positioned by the entity's [`GlobalTransform`] and only affect particle
+/// systems that share a layer with them. An inverted volume treats everything outside
+/// of its shape as its inside, which is useful for keeping particles within a region.
+///
+/// Entering and leaving are detected by comparing a particle's position against where it
+/// was before it was last advanced.
+#[derive(Component, Debug, Clone)]
+pub struct ParticleVolume {
+    pub shape: VolumeShape,
+    pub inverted: bool,
+    pub trigger: VolumeTrigger,
+    pub action: VolumeAction,
+    pub layers: ParticleLayers,
+}
+
+impl ParticleVolume {
+    pub fn new(shape: VolumeShape, trigger: VolumeTrigger, action: VolumeAction) -> Self {
+        Self {
+            shape,
+            inverted: false,
+            trigger,
+            action,
+            layers: ParticleLayers::all(),
+        }
+    }
+
+    /// A volume that kills every particle inside of it.
+    pub fn kill_zone(shape: VolumeShape) -> Self {
+        Self::new(shape, VolumeTrigger::Inside, VolumeAction::Kill)
+    }
+
+    /// A volume that kills every particle outside of it.
+    pub fn bounds(shape: VolumeShape) -> Self {
+        Self::kill_zone(shape).inverted()
+    }
+
+    pub fn inverted(mut self) -> Self {
+        self.inverted = !self.inverted;
+        self
+    }
+
+    pub fn with_layers(mut self, layers: ParticleLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+}
+
+/// Sent when a particle triggers a [`ParticleVolume`] with [`VolumeAction::Event`].
+#[derive(Debug, Clone)]
+pub struct ParticleVolumeEvent {
+    /// The entity of the volume that was triggered.
+    pub volume: Entity,
+    /// The entity of the particle system the particle belongs to.
+    pub particles: Entity,
+    pub trigger: VolumeTrigger,
+    /// The world-space position of the particle.
+    pub position: Vec3,
+    /// The world-space velocity of the particle.
+    pub velocity: Vec3,
+}
+
+/// A volume with its transform resolved for the current frame.
+struct WorldVolume<'a> {
+    entity: Entity,
+    world_to_local: Mat4,
+    volume: &'a ParticleVolume,
+}
+
+impl<'a> WorldVolume<'a> {
+    #[inline]
+    fn contains(&self, position: Vec3) -> bool {
+        let local = self.world_to_local.transform_point3(position);
+        self.volume.shape.contains(local) != self.volume.inverted
+    }
+
+    #[inline]
+    fn is_triggered(&self, previous: Vec3, current: Vec3) -> bool {
+        match self.volume.trigger {
+            VolumeTrigger::Inside => self.contains(current),
+            VolumeTrigger::Enter => !self.contains(previous) && self.contains(current),
+            VolumeTrigger::Exit => self.contains(previous) && !self.contains(current),
+        }
+    }
+}
+
+pub fn apply_particle_volumes(
+    compute_task_pool: Res<ComputeTaskPool>,
+    volumes: Query<(Entity, &ParticleVolume, &GlobalTransform)>,
+    mut particles: Query<(Entity, &mut Particles, Option<&ParticleLayers>)>,
+    mut events: EventWriter<ParticleVolumeEvent>,
+) {
+    let volumes: Vec<WorldVolume> = volumes
+        .iter()
+        .map(|(entity, volume, transform)| WorldVolume {
+            entity,
+            world_to_local: transform.compute_matrix().inverse(),
+            volume,
+        })
+        .collect();
+    if volumes.is_empty() {
+        return;
+    }
+
+    // Events and transfers are gathered from every system before being sent, as transfers
+    // may target a system that is still being updated.
+    type Transfer = (Entity, ParticleParams, f32);
+    let triggered: Mutex<(Vec<ParticleVolumeEvent>, Vec<Transfer>)> = Mutex::default();
+    particles.par_for_each_mut(&compute_task_pool, 8, |(entity, mut particles, layers)| {
+        let layers = layers.copied().unwrap_or_default();
+        let volumes: Vec<&WorldVolume> = volumes
+            .iter()
+            .filter(|volume| volume.volume.layers.intersects(&layers))
+            .collect();
+        if volumes.is_empty() {
+            return;
+        }
+
+        let mut events = Vec::new();
+        let mut transfers = Vec::new();
+        let mut idx = 0;
+        'particles: while idx < particles.len() {
+            let position = particles.positions[idx].xyz();
+            let previous = particles.previous_positions[idx];
+            for volume in volumes.iter() {
+                if !volume.is_triggered(previous, position) {
+                    continue;
+                }
+                match &volume.volume.action {
+                    VolumeAction::Kill => {
+                        particles.remove(idx);
+                        continue 'particles;
+                    }
+                    VolumeAction::Transfer(target) => {
+                        let (params, age) = particles.take(idx);
+                        transfers.push((*target, params, age));
+                        continue 'particles;
+                    }
+                    VolumeAction::Recolor(color) => {
+                        let color = Vec4::from(color.as_rgba_f32());
+                        particles.colors[idx] = color;
+                        particles.start_colors[idx] = color;
+                    }
+                    VolumeAction::Event => events.push(ParticleVolumeEvent {
+                        volume: volume.entity,
+                        particles: entity,
+                        trigger: volume.volume.trigger,
+                        position,
+                        velocity: particles.velocities[idx].xyz(),
+                    }),
+                }
+            }
+            idx += 1;
+        }
+
+        if !events.is_empty() || !transfers.is_empty() {
+            let mut triggered = triggered.lock().unwrap();
+            triggered.0.append(&mut events);
+            triggered.1.append(&mut transfers);
+        }
+    });
+
+    let (triggered_events, transfers) = triggered.into_inner().unwrap();
+    events.send_batch(triggered_events.into_iter());
+    for (target, params, age) in transfers {
+        if let Ok((_, mut particles, _)) = particles.get_mut(target) {
+            particles.spawn_aged(params, age);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world_volume(volume: &ParticleVolume) -> WorldVolume {
+        WorldVolume {
+            entity: Entity::from_raw(0),
+            world_to_local: Mat4::IDENTITY,
+            volume,
+        }
+    }
+
+    /// Two particles that cross the unit cube at the origin in opposite directions.
+    fn crossing_particles() -> Particles {
+        let mut particles = Particles::new(2);
+        particles.spawn(ParticleParams {
+            position: Vec3::X * 2.0,
+            velocity: -Vec3::X * 15.0,
+            lifetime: 1.0,
+            ..Default::default()
+        });
+        particles.spawn(ParticleParams {
+            position: Vec3::X * 0.5,
+            velocity: Vec3::X * 15.0,
+            lifetime: 1.0,
+            ..Default::default()
+        });
+        particles.advance_particles(0.1);
+        particles
+    }
+
+    fn triggered(volume: &ParticleVolume, particles: &Particles) -> Vec<bool> {
+        let volume = world_volume(volume);
+        (0..particles.len())
+            .map(|idx| {
+                let position = particles.positions[idx].xyz();
+                volume.is_triggered(particles.previous_positions[idx], position)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn enter_and_exit_use_previous_positions() {
+        let particles = crossing_particles();
+        let cube = VolumeShape::Box {
+            half_extents: Vec3::ONE,
+        };
+        let enter = ParticleVolume::new(cube.clone(), VolumeTrigger::Enter, VolumeAction::Kill);
+        assert_eq!(triggered(&enter, &particles), vec![true, false]);
+        let exit = ParticleVolume::new(cube.clone(), VolumeTrigger::Exit, VolumeAction::Kill);
+        assert_eq!(triggered(&exit, &particles), vec![false, true]);
+        let inside = ParticleVolume::new(cube, VolumeTrigger::Inside, VolumeAction::Kill);
+        assert_eq!(triggered(&inside, &particles), vec![true, false]);
+    }
+
+    #[test]
+    fn inverted_bounds() {
+        let bounds = ParticleVolume::bounds(VolumeShape::Sphere { radius: 1.0 });
+        let volume = world_volume(&bounds);
+        assert!(!volume.contains(Vec3::ZERO));
+        assert!(volume.contains(Vec3::X * 2.0));
+
+        let particles = crossing_particles();
+        assert_eq!(triggered(&bounds, &particles), vec![false, true]);
+
+        // Inverting again restores the original volume.
+        let exit = ParticleVolume::new(
+            VolumeShape::Sphere { radius: 1.0 },
+            VolumeTrigger::Exit,
+            VolumeAction::Kill,
+        )
+        .inverted();
+        assert_eq!(triggered(&exit, &particles), vec![true, false]);
+        assert!(world_volume(&exit.inverted()).contains(Vec3::ZERO));
+    }
+
+    #[test]
+    fn transfers_keep_lifetime() {
+        let mut source = Particles::new(2);
+        for _ in 0..2 {
+            source.spawn(ParticleParams {
+                lifetime: 2.0,
+                ..Default::default()
+            });
+        }
+        source.advance_particles(0.5);
+        source.colors[0] = Vec4::ZERO;
+
+        let mut target = Particles::new(1);
+        target.advance_particles(3.0);
+        let (params, age) = source.take(0);
+        target.spawn_aged(params, age);
+
+        assert_eq!(source.len(), 1);
+        // SAFE: both systems have a particle at index 0.
+        unsafe {
+            assert_eq!(target.lifetime_ratio(0), source.lifetime_ratio(0));
+        }
+        assert_eq!(target.expirations[0] - target.lifetime, 1.5);
+        assert_eq!(target.colors[0], Vec4::ONE);
+    }
+}